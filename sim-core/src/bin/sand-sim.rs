@@ -0,0 +1,259 @@
+//! Headless runner: load a snapshot or scene, step it, write the result.
+
+use sim_core::{Simulation, heat_level};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+const USAGE: &str = "\
+usage: sand-sim [options]
+
+  -i, --input <file>     snapshot or scene file to start from (default: empty 480x270 grid)
+  -m, --materials <file> material table to use instead of the built-in one
+  -n, --ticks <n>        ticks to run (default 0)
+  -s, --seed <n>         seed for scenes and empty grids, or reseed a snapshot's rng
+  -o, --output <file>    write the final state as a snapshot
+  -p, --png <file>       write the final frame as a png
+  -f, --frames <dir>     write a png every --every ticks into <dir>
+  -e, --every <n>        frame interval in ticks (default 1)
+  -t, --threads <n>      threads to step with, results don't depend on it (default: all cores)
+      --glow             composite the glow layer onto pngs
+      --heat             overlay the heat map onto pngs
+  -q, --quiet            only print errors
+  -h, --help             show this message";
+
+// blur radius for the glow layer, roughly the web UI's 8px blur at its default 2x scale
+const GLOW_RADIUS: usize = 4;
+
+#[derive(Default)]
+struct Options {
+    input: Option<PathBuf>,
+    materials: Option<PathBuf>,
+    ticks: u32,
+    seed: Option<u64>,
+    output: Option<PathBuf>,
+    png: Option<PathBuf>,
+    frames: Option<PathBuf>,
+    every: u32,
+    threads: Option<usize>,
+    glow: bool,
+    heat: bool,
+    quiet: bool,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut opts = Options { every: 1, ..Default::default() };
+    let mut args = args.peekable();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{name} expects a value"));
+        match arg.as_str() {
+            "-i" | "--input" => opts.input = Some(value(&arg)?.into()),
+            "-m" | "--materials" => opts.materials = Some(value(&arg)?.into()),
+            "-n" | "--ticks" => opts.ticks = value(&arg)?.parse().map_err(|_| "--ticks expects a number")?,
+            "-s" | "--seed" => opts.seed = Some(value(&arg)?.parse().map_err(|_| "--seed expects a number")?),
+            "-o" | "--output" => opts.output = Some(value(&arg)?.into()),
+            "-p" | "--png" => opts.png = Some(value(&arg)?.into()),
+            "-f" | "--frames" => opts.frames = Some(value(&arg)?.into()),
+            "-e" | "--every" => opts.every = value(&arg)?.parse().map_err(|_| "--every expects a number")?,
+            "-t" | "--threads" => opts.threads = Some(value(&arg)?.parse().map_err(|_| "--threads expects a number")?),
+            "--glow" => opts.glow = true,
+            "--heat" => opts.heat = true,
+            "-q" | "--quiet" => opts.quiet = true,
+            "-h" | "--help" => return Ok(None),
+            other => return Err(format!("unknown argument '{other}'")),
+        }
+    }
+
+    if opts.every == 0 {
+        return Err("--every must be at least 1".into());
+    }
+    if opts.threads == Some(0) {
+        return Err("--threads must be at least 1".into());
+    }
+    Ok(Some(opts))
+}
+
+fn load(opts: &Options) -> Result<Simulation, String> {
+    // has to happen before anything looks up a material
+    if let Some(path) = &opts.materials {
+        let src = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        sim_core::load_materials(&src).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+
+    let Some(path) = &opts.input else {
+        return Ok(Simulation::with_seed(480, 270, opts.seed.unwrap_or(sim_core::DEFAULT_SEED)));
+    };
+    let bytes = fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    // snapshots start with their magic, anything else is treated as a scene
+    if bytes.starts_with(b"SSIM") {
+        let mut sim = Simulation::load(&bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+        if let Some(seed) = opts.seed {
+            sim.reseed(seed);
+        }
+        Ok(sim)
+    } else {
+        let src = String::from_utf8(bytes).map_err(|_| format!("{}: neither a snapshot nor a text scene", path.display()))?;
+        Simulation::from_scene(&src, opts.seed).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// Same ramp as the web UI heat overlay: dark blue -> cyan -> yellow -> red
+fn heat_colour(h: u8) -> [u8; 4] {
+    let t = (h as f32 / 255.0).sqrt();
+    let (r, g, b) = if t < 0.25 {
+        let s = t / 0.25;
+        (0.0, s * 100.0, 80.0 + s * 175.0)
+    } else if t < 0.5 {
+        let s = (t - 0.25) / 0.25;
+        (0.0, 100.0 + s * 155.0, 255.0 - s * 255.0)
+    } else if t < 0.75 {
+        let s = (t - 0.5) / 0.25;
+        (s * 255.0, 255.0, 0.0)
+    } else {
+        let s = (t - 0.75) / 0.25;
+        (255.0, 255.0 - s * 175.0, 0.0)
+    };
+    [r.round() as u8, g.round() as u8, b.round() as u8, 200]
+}
+
+/// Separable box blur of a premultiplied rgb layer
+fn box_blur(src: &[f32], w: usize, h: usize, radius: usize) -> Vec<f32> {
+    let mut tmp = vec![0.0; src.len()];
+    let mut out = vec![0.0; src.len()];
+    let norm = 1.0 / (2 * radius + 1) as f32;
+
+    for y in 0..h {
+        for x in 0..w {
+            let mut acc = [0.0f32; 3];
+            for sx in x.saturating_sub(radius)..=(x + radius).min(w - 1) {
+                let p = (y * w + sx) * 3;
+                for c in 0..3 {
+                    acc[c] += src[p + c];
+                }
+            }
+            let p = (y * w + x) * 3;
+            for c in 0..3 {
+                tmp[p + c] = acc[c] * norm;
+            }
+        }
+    }
+    for y in 0..h {
+        for x in 0..w {
+            let mut acc = [0.0f32; 3];
+            for sy in y.saturating_sub(radius)..=(y + radius).min(h - 1) {
+                let p = (sy * w + x) * 3;
+                for c in 0..3 {
+                    acc[c] += tmp[p + c];
+                }
+            }
+            let p = (y * w + x) * 3;
+            for c in 0..3 {
+                out[p + c] = acc[c] * norm;
+            }
+        }
+    }
+    out
+}
+
+/// Flatten the sim layers onto black, the way the web UI draws them
+fn render(sim: &Simulation, glow: bool, heat: bool) -> Vec<u8> {
+    let (w, h) = (sim.width() as usize, sim.height() as usize);
+    let pixels = sim.pixels();
+
+    let mut rgb: Vec<f32> = pixels.chunks_exact(4).flat_map(|p| (0..3).map(move |c| p[c] as f32 * p[3] as f32 / 255.0)).collect();
+
+    if glow {
+        let layer: Vec<f32> = sim.glow_pixels().chunks_exact(4).flat_map(|p| (0..3).map(move |c| p[c] as f32 * p[3] as f32 / 255.0)).collect();
+        // additive, like the canvas "lighter" composite
+        for (o, g) in rgb.iter_mut().zip(box_blur(&layer, w, h, GLOW_RADIUS)) {
+            *o += g;
+        }
+    }
+
+    if heat {
+        for (i, &t) in sim.temperatures().iter().enumerate() {
+            let c = heat_colour(heat_level(t));
+            let a = c[3] as f32 / 255.0;
+            for k in 0..3 {
+                rgb[i * 3 + k] = rgb[i * 3 + k] * (1.0 - a) + c[k] as f32 * a;
+            }
+        }
+    }
+
+    rgb.chunks_exact(3).flat_map(|p| [p[0].min(255.0) as u8, p[1].min(255.0) as u8, p[2].min(255.0) as u8, 255]).collect()
+}
+
+fn write_png(path: &Path, sim: &Simulation, opts: &Options) -> Result<(), String> {
+    let err = |e: &dyn std::fmt::Display| format!("{}: {e}", path.display());
+    let file = File::create(path).map_err(|e| err(&e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), sim.width(), sim.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| err(&e))?;
+    writer.write_image_data(&render(sim, opts.glow, opts.heat)).map_err(|e| err(&e))
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    let mut sim = load(opts)?;
+    // threads only exist natively; a wasm32 build of the runner steps on one
+    #[cfg(not(target_arch = "wasm32"))]
+    sim.set_threads(opts.threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())));
+
+    if let Some(dir) = &opts.frames {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        write_png(&dir.join("frame_000000.png"), &sim, opts)?;
+    }
+
+    let start = Instant::now();
+    let mut done = 0;
+    while done < opts.ticks {
+        // step in frame-sized batches so every frame lands on a multiple of --every
+        let batch = if opts.frames.is_some() { opts.every.min(opts.ticks - done) } else { opts.ticks - done };
+        sim.step(batch);
+        done += batch;
+        if let Some(dir) = &opts.frames {
+            write_png(&dir.join(format!("frame_{done:06}.png")), &sim, opts)?;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    if !opts.quiet {
+        let secs = elapsed.as_secs_f64();
+        let rate = if secs > 0.0 { opts.ticks as f64 / secs } else { 0.0 };
+        println!("{}x{}: {} ticks in {:.3}s ({:.1} ticks/s)", sim.width(), sim.height(), opts.ticks, secs, rate);
+    }
+
+    if let Some(path) = &opts.output {
+        fs::write(path, sim.save()).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    if let Some(path) = &opts.png {
+        write_png(path, &sim, opts)?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("sand-sim: {e}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("sand-sim: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
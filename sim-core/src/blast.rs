@@ -0,0 +1,146 @@
+//! Explosions.
+//!
+//! A detonating cell sets off every grain of its kind within its `blast_radius` at once,
+//! and the more of them there are the further and harder the blast reaches. The blast
+//! travels out along rays. Whatever can move is flung away from the centre, slower the
+//! denser it is, and soaks up part of the blast for what lies behind it. Solids stop it,
+//! so walls shelter what is behind them, and breakable ones closer in than their strength
+//! allows are blown apart. The gas given off raises the air pressure and a gust blows out
+//! from the centre.
+
+use crate::chunks::CHUNK_SIZE;
+use crate::{Cell, Group, Material, SUBCELL, SimAPI, props};
+
+/// Farthest a blast reaches, however big the charge. It's part of a cell update, so it has
+/// to stay within a chunk's reach.
+const MAX_RADIUS: i32 = CHUNK_SIZE as i32 / 2;
+
+/// Pressure of one grain's blast a cell away from it
+const BLAST: f32 = 60.0;
+
+/// Speed given per unit of blast pressure to something as dense as water
+const FLING: f32 = 0.3;
+
+/// Share of the blast a movable cell lets through to what is behind it
+const SOAK: f32 = 0.8;
+
+/// Air pressure the gas of one grain adds
+const GAS: f32 = 4.0;
+
+/// Gust blowing out from the centre of one grain's blast, in cells per tick
+const GUST: f32 = 0.5;
+
+/// Side of the square of cells a blast can reach
+const SPAN: usize = 2 * MAX_RADIUS as usize + 1;
+
+/// Detonate the cell, along with every other cell of its material within `radius`
+pub(crate) fn explode(api: &mut SimAPI, radius: i32) {
+    let charge_material = api.get(0, 0).material;
+    let radius = radius.min(MAX_RADIUS);
+
+    // the whole charge goes up at once, leaving a fireball; packed tighter it's bigger
+    let (mut charge, mut sum_x, mut sum_y) = (0u32, 0, 0);
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy > radius * radius || !api.in_bounds(dx, dy) {
+                continue;
+            }
+            let m = api.get(dx, dy).material;
+            let fire = if m == charge_material {
+                charge += 1;
+                sum_x += dx;
+                sum_y += dy;
+                true
+            } else {
+                matches!(m, Material::Empty | Material::Smoke | Material::Steam) && api.one_in(3)
+            };
+            if fire {
+                let ra = api.rand_u32() as u8;
+                api.set(dx, dy, Cell { material: Material::Fire, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+            }
+        }
+    }
+
+    // it goes off from the middle of the charge, not the grain that lit it
+    let (cx, cy) = ((sum_x as f32 / charge as f32).round() as i32, (sum_y as f32 / charge as f32).round() as i32);
+    let power = (charge as f32).sqrt();
+    let reach = ((radius as f32 * (charge as f32).cbrt()) as i32).min(MAX_RADIUS - cx.abs().max(cy.abs()));
+    api.add_pressure(cx, cy, GAS * charge as f32);
+    let gust = GUST * power;
+    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        api.add_wind(cx + dx * reach / 2, cy + dy * reach / 2, dx as f32 * gust, dy as f32 * gust);
+    }
+
+    // each cell takes the blast once, from the first ray to reach it
+    let mut hit = [false; SPAN * SPAN];
+    let mut flung = Vec::new();
+    let rays = 8 * reach;
+    for r in 0..rays {
+        let angle = r as f32 * std::f32::consts::TAU / rays as f32;
+        let (ux, uy) = (angle.cos(), angle.sin());
+        let mut strength = BLAST * power;
+        for d in 1..=reach {
+            let (dx, dy) = (cx + (ux * d as f32).round() as i32, cy + (uy * d as f32).round() as i32);
+            if !api.in_bounds(dx, dy) {
+                break;
+            }
+            let cell = api.get(dx, dy);
+            let p = props(cell.material);
+            let pressure = strength / d as f32;
+            let seen = std::mem::replace(&mut hit[(dy + MAX_RADIUS) as usize * SPAN + (dx + MAX_RADIUS) as usize], true);
+
+            match p.group {
+                Group::Empty => {}
+                Group::Solid => {
+                    if !seen && p.strength > 0.0 && pressure > p.strength {
+                        let ra = api.rand_u32() as u8;
+                        let (vx, vy) = fling(p.rubble, pressure, ux, uy);
+                        api.set(dx, dy, Cell { material: p.rubble, ra, rb: 0, clock: 0, vx, vy, sx: 0, sy: 0 });
+                    }
+                    break;
+                }
+                _ => {
+                    if !seen && cell.material != Material::Fire {
+                        flung.push((d, dx, dy, fling(cell.material, pressure, ux, uy)));
+                    }
+                    if matches!(p.group, Group::Powder | Group::Liquid) {
+                        strength *= SOAK;
+                    }
+                }
+            }
+        }
+    }
+
+    // the first tick of the flight happens now, outermost first so there's room behind
+    flung.sort_by_key(|f| std::cmp::Reverse(f.0));
+    for (_, dx, dy, v) in flung {
+        shove(api, dx, dy, v);
+    }
+}
+
+/// Move the cell at the offset along the velocity as far as it's empty, leaving it with
+/// that velocity
+fn shove(api: &mut SimAPI, mut x: i32, mut y: i32, (vx, vy): (i16, i16)) {
+    let (mut rx, mut ry) = ((vx / SUBCELL) as i32, (vy / SUBCELL) as i32);
+    while rx != 0 || ry != 0 {
+        let (sx, sy) = (rx.signum(), ry.signum());
+        let (nx, ny) = (x + sx, y + sy);
+        if nx.abs().max(ny.abs()) > MAX_RADIUS || !api.in_bounds(nx, ny) || api.get(nx, ny).material != Material::Empty {
+            break;
+        }
+        api.move_cell(x, y, nx, ny);
+        (x, y, rx, ry) = (nx, ny, rx - sx, ry - sy);
+    }
+    let cell = api.get(x, y);
+    api.set(x, y, Cell { vx, vy, ..cell });
+}
+
+/// Velocity a blast of this pressure gives a cell of the material, heading along (ux, uy)
+fn fling(m: Material, pressure: f32, ux: f32, uy: f32) -> (i16, i16) {
+    if m == Material::Empty {
+        return (0, 0);
+    }
+    let p = props(m);
+    let speed = (pressure * FLING * 1000.0 / p.density).min(p.terminal_velocity as f32) * SUBCELL as f32;
+    ((ux * speed) as i16, (uy * speed) as i16)
+}
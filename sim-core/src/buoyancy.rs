@@ -0,0 +1,169 @@
+//! Floating solids.
+//!
+//! Solids marked `buoyant` move as rigid lumps: every connected group of cells of one
+//! such material that touches a liquid is found by flood fill after the cells have moved
+//! each tick. The liquid around it weighs its `density` for every cell of the lump below
+//! the highest liquid lying beside it. If that is less than the lump's own weight the lump
+//! sinks, if it would still be more with the lump a cell higher it rises, so ice floats
+//! with a sliver showing, wood rides high and ice in oil goes to the bottom. It moves a
+//! cell at a time, more often the bigger the difference, so it bobs at the surface and
+//! something nearly as dense as the liquid drifts slowly.
+//!
+//! A lump moves column by column: each vertical run of its cells shifts along by one and
+//! the fluid it pushes into fills in behind it. Anything but fluid in the way holds the
+//! whole lump, so it rests on the bottom or against whatever is above it.
+
+use crate::pressure::holds_pressure;
+use crate::{Cell, Group, Material, Simulation, props};
+
+/// Chance a tick that a lump moves, per unit of the difference between its weight and that
+/// of the liquid it displaces, as a share of its weight
+const RESPONSE: f32 = 2.0;
+
+impl Simulation {
+    /// Float or sink every lump of buoyant solid touching a liquid by a cell
+    pub(crate) fn float_solids(&mut self) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut buoyant = [false; 256];
+        for m in Material::all() {
+            buoyant[m.id() as usize] = props(m).buoyant;
+        }
+        if !buoyant.contains(&true) {
+            return;
+        }
+
+        let mut stack = Vec::new();
+        let mut lump = Vec::new();
+        for cy in 0..self.chunks.rows {
+            for cx in 0..self.chunks.cols {
+                if !self.chunks.is_awake(cx, cy) {
+                    continue;
+                }
+                let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+                for y in y0 as usize..y1 as usize {
+                    for x in x0 as usize..x1 as usize {
+                        let i = y * w + x;
+                        let material = self.cells[i].material;
+                        if !buoyant[material.id() as usize] || !self.fill(i) {
+                            continue;
+                        }
+
+                        // flood fill the lump
+                        stack.push((x, y));
+                        lump.clear();
+                        let (mut left, mut top, mut right, mut bottom) = (x, y, x, y);
+                        while let Some((x, y)) = stack.pop() {
+                            lump.push((x, y));
+                            (left, top, right, bottom) = (left.min(x), top.min(y), right.max(x), bottom.max(y));
+                            for (nx, ny, ok) in [(x, y.wrapping_sub(1), y > 0), (x.wrapping_sub(1), y, x > 0), (x + 1, y, x + 1 < w), (x, y + 1, y + 1 < h)] {
+                                if !ok {
+                                    continue;
+                                }
+                                let n = ny * w + nx;
+                                if self.cells[n].material == material && self.fill(n) {
+                                    stack.push((nx, ny));
+                                }
+                            }
+                        }
+
+                        // the waterline is the highest liquid just off either end of its rows or
+                        // under its columns, so neither pockets inside it, nor drops falling past
+                        // or a puddle lying on top, count
+                        let (mut rows, mut columns) = (vec![(usize::MAX, 0); bottom - top + 1], vec![0; right - left + 1]);
+                        for &(x, y) in &lump {
+                            let row = &mut rows[y - top];
+                            *row = (row.0.min(x), row.1.max(x));
+                            columns[x - left] = columns[x - left].max(y);
+                        }
+                        let ends = rows.iter().zip(top..).flat_map(|(&(l, r), y)| [(l.wrapping_sub(1), y), (r + 1, y)]);
+                        let unders = columns.iter().zip(left..).map(|(&y, x)| (x, y + 1));
+                        let (mut surface, mut touching, mut liquid_density) = (usize::MAX, 0, 0.0);
+                        for (x, y) in ends.chain(unders) {
+                            if x >= w || y >= h {
+                                continue;
+                            }
+                            let p = props(self.cells[y * w + x].material);
+                            if p.group == Group::Liquid && (y + 1 == h || self.cells[(y + 1) * w + x].material != Material::Empty) {
+                                surface = surface.min(y);
+                                touching += 1;
+                                liquid_density += p.density;
+                            }
+                        }
+                        if touching == 0 {
+                            continue;
+                        }
+
+                        let liquid_density = liquid_density / touching as f32;
+                        let weight = lump.len() as f32 * props(material).density;
+                        let displaced = |depth: usize| lump.iter().filter(|&&(_, y)| y >= surface + depth).count() as f32 * liquid_density;
+                        let (dy, force) = if displaced(0) < weight {
+                            (1, weight - displaced(0))
+                        } else if displaced(1) >= weight {
+                            (-1, displaced(0) - weight)
+                        } else {
+                            continue;
+                        };
+                        if (self.rng_next() as f32 / u32::MAX as f32) < RESPONSE * force / weight {
+                            self.shift_lump(&lump, material, dy);
+                        }
+                    }
+                }
+            }
+        }
+        self.clear_filled();
+    }
+
+    /// Move a lump of `material` a row up or down if there is fluid to trade places with
+    /// at the end of each of its columns
+    fn shift_lump(&mut self, lump: &[(usize, usize)], material: Material, dy: i32) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut runs = Vec::new();
+        for &(x, top) in lump {
+            if top > 0 && self.cells[(top - 1) * w + x].material == material {
+                continue;
+            }
+            let mut bottom = top;
+            while bottom + 1 < h && self.cells[(bottom + 1) * w + x].material == material {
+                bottom += 1;
+            }
+            // the run and the fluid cell it moves into
+            let (y0, y1) = if dy < 0 { (top.wrapping_sub(1), bottom) } else { (top, bottom + 1) };
+            if y0 >= h || y1 >= h || !holds_pressure(self.cells[if dy < 0 { y0 } else { y1 } * w + x].material) {
+                return;
+            }
+            runs.push((x, y0, y1));
+        }
+
+        for (x, y0, y1) in runs {
+            shift_column(&mut self.cells, w, x, y0, y1, dy);
+            shift_column(&mut self.temp, w, x, y0, y1, dy);
+            shift_column(&mut self.pressure, w, x, y0, y1, dy);
+            shift_column(&mut self.oxygen, w, x, y0, y1, dy);
+            for y in y0..=y1 {
+                self.cells[y * w + x] = Cell { clock: self.generation, ..self.cells[y * w + x] };
+                self.chunks.wake(x as i32, y as i32);
+                self.chunks.warm(x as i32, y as i32);
+                self.chunks.pressurize(x as i32, y as i32);
+                self.chunks.deplete(x as i32, y as i32);
+            }
+        }
+    }
+}
+
+/// Shift rows y0 to y1 of column x along by one in direction `dy`, the end that falls off
+/// wrapping round to the other
+fn shift_column<T: Copy>(v: &mut [T], w: usize, x: usize, y0: usize, y1: usize, dy: i32) {
+    if dy < 0 {
+        let first = v[y0 * w + x];
+        for y in y0..y1 {
+            v[y * w + x] = v[(y + 1) * w + x];
+        }
+        v[y1 * w + x] = first;
+    } else {
+        let last = v[y1 * w + x];
+        for y in (y0..y1).rev() {
+            v[(y + 1) * w + x] = v[y * w + x];
+        }
+        v[y0 * w + x] = last;
+    }
+}
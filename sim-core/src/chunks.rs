@@ -0,0 +1,209 @@
+//! Activity tracking so `step` can skip regions where nothing is happening.
+//!
+//! The grid is split into `CHUNK_SIZE` squares. A chunk is updated on a tick only if
+//! something woke it during the previous one. Any write that changes a cell wakes the
+//! chunk it lands in, plus the neighbouring chunk when it lands on a chunk border,
+//! since cells there read across it.
+//!
+//! A cell update never reaches more than `CHUNK_SIZE / 2` cells outside its own chunk, so
+//! chunks two apart never touch the same cells. A cell moves at most its terminal velocity
+//! along each axis, then reads the cell past where it stopped, steps a cell diagonally or,
+//! as a liquid, spreads up to its dispersion sideways on top of that. The material table
+//! rejects materials whose terminal velocity and dispersion add up to more than the limit;
+//! water, at 8 and 7, reaches 15. Heat radiates up to 8 cells, before the cell moves.
+//! `step` relies on that to update one colour of a 2x2 checkerboard of chunks in parallel,
+//! which is also why the flags written during a tick are atomic.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub(crate) const CHUNK_SIZE: u32 = 32;
+
+/// Most cells a cell update may reach past its own, moving or looking
+pub(crate) const MAX_REACH: u32 = CHUNK_SIZE / 2;
+
+// `step` draws one rng word per chunk and uses a bit of it per row
+const _: () = assert!(CHUNK_SIZE <= 32);
+
+pub(crate) struct Chunks {
+    pub cols: u32,
+    pub rows: u32,
+    /// chunks being updated this tick
+    awake: Vec<bool>,
+    /// chunks woken during this tick, updated next tick
+    next: Vec<AtomicBool>,
+    /// chunks touched since pixels were last written
+    dirty: Vec<AtomicBool>,
+    /// chunks that may hold cells away from ambient temperature, diffused, carried in or held by a source
+    warm: Vec<AtomicBool>,
+    /// chunks that may hold air above or below ambient pressure
+    pressurized: Vec<AtomicBool>,
+    /// chunks that may hold air short of oxygen
+    depleted: Vec<AtomicBool>,
+}
+
+fn flags(n: usize, v: bool) -> Vec<AtomicBool> {
+    (0..n).map(|_| AtomicBool::new(v)).collect()
+}
+
+impl Chunks {
+    pub fn new(width: u32, height: u32) -> Self {
+        let cols = width.div_ceil(CHUNK_SIZE);
+        let rows = height.div_ceil(CHUNK_SIZE);
+        let n = (cols * rows) as usize;
+        // everything starts awake so the first tick sees the whole grid
+        Chunks { cols, rows, awake: vec![false; n], next: flags(n, true), dirty: flags(n, true), warm: flags(n, true), pressurized: flags(n, false), depleted: flags(n, false) }
+    }
+
+    #[inline]
+    fn index(&self, cx: u32, cy: u32) -> usize {
+        (cy * self.cols + cx) as usize
+    }
+
+    /// Wake the chunk holding (x, y), and its neighbours if (x, y) is on their border
+    #[inline]
+    pub fn wake(&self, x: i32, y: i32) {
+        let size = CHUNK_SIZE as i32;
+        let cx0 = ((x - 1).max(0) / size) as u32;
+        let cy0 = ((y - 1).max(0) / size) as u32;
+        let cx1 = (((x + 1) / size) as u32).min(self.cols - 1);
+        let cy1 = (((y + 1) / size) as u32).min(self.rows - 1);
+        for cy in cy0..=cy1 {
+            for cx in cx0..=cx1 {
+                let c = self.index(cx, cy);
+                self.next[c].store(true, Ordering::Relaxed);
+                self.dirty[c].store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn wake_all(&mut self) {
+        for f in self.next.iter_mut().chain(&mut self.dirty).chain(&mut self.warm).chain(&mut self.pressurized).chain(&mut self.depleted) {
+            *f.get_mut() = true;
+        }
+    }
+
+    /// Start a tick: chunks woken last tick become the awake set
+    pub fn advance(&mut self) {
+        for ((a, n), d) in self.awake.iter_mut().zip(&mut self.next).zip(&mut self.dirty) {
+            *a = std::mem::replace(n.get_mut(), false);
+            *d.get_mut() |= *a;
+        }
+    }
+
+    #[inline]
+    pub fn is_awake(&self, cx: u32, cy: u32) -> bool {
+        self.awake[self.index(cx, cy)]
+    }
+
+    /// Number of chunks that will be updated on the next tick
+    pub fn pending_count(&self) -> usize {
+        self.next.iter().filter(|a| a.load(Ordering::Relaxed)).count()
+    }
+
+    /// Chunks that will be updated on the next tick, row-major
+    pub fn pending(&self) -> Vec<bool> {
+        self.next.iter().map(|a| a.load(Ordering::Relaxed)).collect()
+    }
+
+    /// Restore the set of chunks to update on the next tick
+    pub fn set_pending(&mut self, pending: &[bool]) {
+        for (n, &p) in self.next.iter_mut().zip(pending) {
+            *n.get_mut() = p;
+        }
+    }
+
+    /// Take the dirty flag of a chunk, clearing it
+    #[inline]
+    pub fn take_dirty(&mut self, cx: u32, cy: u32) -> bool {
+        let c = self.index(cx, cy);
+        std::mem::replace(self.dirty[c].get_mut(), false)
+    }
+
+    /// Mark the chunk holding (x, y) as possibly away from ambient temperature
+    #[inline]
+    pub fn warm(&self, x: i32, y: i32) {
+        let c = self.index(x as u32 / CHUNK_SIZE, y as u32 / CHUNK_SIZE);
+        self.warm[c].store(true, Ordering::Relaxed);
+    }
+
+    /// Chunks that need diffusing: warm ones and their neighbours, which heat can spread into
+    pub fn heat_targets(&self) -> Vec<(u32, u32)> {
+        self.near(&self.warm)
+    }
+
+    /// Chunks flagged in `flags`, and their neighbours
+    fn near(&self, flags: &[AtomicBool]) -> Vec<(u32, u32)> {
+        let mut out = Vec::new();
+        for cy in 0..self.rows {
+            for cx in 0..self.cols {
+                let near_flagged =
+                    (cy.saturating_sub(1)..=(cy + 1).min(self.rows - 1)).any(|ny| (cx.saturating_sub(1)..=(cx + 1).min(self.cols - 1)).any(|nx| flags[self.index(nx, ny)].load(Ordering::Relaxed)));
+                if near_flagged {
+                    out.push((cx, cy));
+                }
+            }
+        }
+        out
+    }
+
+    #[inline]
+    pub fn set_warm(&self, cx: u32, cy: u32, warm: bool) {
+        let c = self.index(cx, cy);
+        self.warm[c].store(warm, Ordering::Relaxed);
+    }
+
+    /// Mark the chunk holding (x, y) as possibly away from ambient pressure
+    #[inline]
+    pub fn pressurize(&self, x: i32, y: i32) {
+        let c = self.index(x as u32 / CHUNK_SIZE, y as u32 / CHUNK_SIZE);
+        self.pressurized[c].store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the chunk holding (x, y), or one it borders there, may hold pressure
+    #[inline]
+    pub fn pressurized_near(&self, x: i32, y: i32) -> bool {
+        let size = CHUNK_SIZE as i32;
+        let cx0 = ((x - 1).max(0) / size) as u32;
+        let cy0 = ((y - 1).max(0) / size) as u32;
+        let cx1 = (((x + 1) / size) as u32).min(self.cols - 1);
+        let cy1 = (((y + 1) / size) as u32).min(self.rows - 1);
+        (cy0..=cy1).any(|cy| (cx0..=cx1).any(|cx| self.pressurized[self.index(cx, cy)].load(Ordering::Relaxed)))
+    }
+
+    /// Chunks pressure can spread through: pressurized ones and their neighbours
+    pub fn pressure_targets(&self) -> Vec<(u32, u32)> {
+        self.near(&self.pressurized)
+    }
+
+    #[inline]
+    pub fn set_pressurized(&self, cx: u32, cy: u32, pressurized: bool) {
+        let c = self.index(cx, cy);
+        self.pressurized[c].store(pressurized, Ordering::Relaxed);
+    }
+
+    /// Mark the chunk holding (x, y) as possibly short of oxygen
+    #[inline]
+    pub fn deplete(&self, x: i32, y: i32) {
+        let c = self.index(x as u32 / CHUNK_SIZE, y as u32 / CHUNK_SIZE);
+        self.depleted[c].store(true, Ordering::Relaxed);
+    }
+
+    /// Chunks oxygen can spread through: depleted ones and their neighbours
+    pub fn oxygen_targets(&self) -> Vec<(u32, u32)> {
+        self.near(&self.depleted)
+    }
+
+    #[inline]
+    pub fn set_depleted(&self, cx: u32, cy: u32, depleted: bool) {
+        let c = self.index(cx, cy);
+        self.depleted[c].store(depleted, Ordering::Relaxed);
+    }
+
+    /// Cell bounds of a chunk as (x0, y0, x1, y1), end exclusive
+    #[inline]
+    pub fn bounds(&self, cx: u32, cy: u32, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let x0 = cx * CHUNK_SIZE;
+        let y0 = cy * CHUNK_SIZE;
+        (x0, y0, (x0 + CHUNK_SIZE).min(width), (y0 + CHUNK_SIZE).min(height))
+    }
+}
@@ -0,0 +1,173 @@
+//! Temperature, in degrees Celsius, one value per cell.
+//!
+//! Each tick neighbouring cells exchange heat in proportion to their temperature
+//! difference and the conductivity of both materials. What one cell gains its neighbour
+//! loses, so exchange alone conserves energy; how far that moves each temperature depends
+//! on the material's heat capacity. On top of that every cell leaks a little towards the
+//! ambient temperature, standing in for the air around the scene, so closed spaces cool
+//! down too, and the more heat a material holds the slower it leaks. Temperature moves
+//! with the material when cells move.
+//!
+//! Glowing hot materials also radiate: rays go out from them in every direction and warm
+//! the first opaque cell they meet, across air, gas and flames, by less the farther it is.
+//! The emitter loses the heat it gives away.
+
+use crate::chunks::CHUNK_SIZE;
+use crate::{Material, SimAPI, Simulation, materials, par, props};
+
+/// Temperature of a fresh grid, and what everything slowly cools or warms towards
+pub const AMBIENT_TEMPERATURE: f32 = 20.0;
+
+/// Fraction of the difference to ambient a material with air's heat capacity loses per tick
+const AMBIENT_COOLING: f32 = 0.005;
+
+/// Cells this close to ambient are snapped to it, so cooled chunks can stop diffusing
+const SNAP: f32 = 0.1;
+
+/// Cells a radiant ray travels before giving up
+const RADIANT_RANGE: i32 = 8;
+
+// radiating is part of a cell update, so it has to stay within a chunk's reach
+const _: () = assert!(RADIANT_RANGE <= CHUNK_SIZE as i32 / 2);
+
+/// Below this an emitter isn't glowing and conduction dwarfs what it would radiate
+const RADIANT_MIN_TEMPERATURE: f32 = 300.0;
+
+/// Share of the temperature difference a ray carries at distance 1, times emissivity
+const RADIANCE: f32 = 0.007;
+
+/// Unit vectors of the rays, every 22.5 degrees
+const RAYS: [(f32, f32); 16] = [
+    (1.0, 0.0),
+    (0.924, 0.383),
+    (0.707, 0.707),
+    (0.383, 0.924),
+    (0.0, 1.0),
+    (-0.383, 0.924),
+    (-0.707, 0.707),
+    (-0.924, 0.383),
+    (-1.0, 0.0),
+    (-0.924, -0.383),
+    (-0.707, -0.707),
+    (-0.383, -0.924),
+    (0.0, -1.0),
+    (0.383, -0.924),
+    (0.707, -0.707),
+    (0.924, -0.383),
+];
+
+/// Radiate heat from this cell to the opaque cells in line of sight
+pub(crate) fn radiate(api: &mut SimAPI, emissivity: f32) {
+    let t = api.temp_here();
+    if t < RADIANT_MIN_TEMPERATURE {
+        return;
+    }
+    // cells walled in by their neighbours can't see anything
+    if [(0, -1), (-1, 0), (1, 0), (0, 1)].iter().all(|&(dx, dy)| props(api.get(dx, dy).material).opaque) {
+        return;
+    }
+
+    let mut emitted = 0.0;
+    for (ux, uy) in RAYS {
+        for d in 1..=RADIANT_RANGE {
+            let (dx, dy) = ((ux * d as f32).round() as i32, (uy * d as f32).round() as i32);
+            if !api.in_bounds(dx, dy) {
+                break;
+            }
+            if !props(api.get(dx, dy).material).opaque {
+                continue;
+            }
+            let target = api.get_temp(dx, dy);
+            if target < t {
+                let energy = emissivity * RADIANCE * (t - target) / d as f32;
+                api.add_heat(dx, dy, energy);
+                emitted += energy;
+            }
+            break;
+        }
+    }
+    if emitted > 0.0 {
+        api.add_heat(0, 0, -emitted);
+        // it keeps radiating whether or not anything else happens here
+        api.keep_awake();
+    }
+}
+
+/// Position on the 0-255 ramp of the heat overlays, 5 degrees a step from -20
+pub fn heat_level(celsius: f32) -> u8 {
+    ((celsius + 20.0) / 5.0).clamp(0.0, 255.0) as u8
+}
+
+impl Simulation {
+    pub(crate) fn diffuse_heat(&mut self) {
+        let w = self.width as usize;
+        let h = self.height as usize;
+
+        // chunks at ambient with nothing warm around them stay that way, skip them
+        let targets = self.chunks.heat_targets();
+
+        // conductance between two materials is symmetric, so what one cell gains the other loses
+        let table = materials::table();
+        // per material 1 / (4 * heat capacity), sharing a tick's exchange over the neighbours
+        let mut inertia = [0.0f32; 256];
+        // and the share of its difference to ambient it leaks each tick
+        let mut leak = [0.0f32; 256];
+        for m in Material::all() {
+            inertia[m.id() as usize] = 0.25 / table.props(m).heat_capacity;
+            leak[m.id() as usize] = AMBIENT_COOLING / table.props(m).heat_capacity;
+        }
+
+        // each row of chunks owns its band of temp_next, so bands diffuse in parallel
+        let band_len = w * CHUNK_SIZE as usize;
+        let mut bands: Vec<(u32, &mut [f32])> = self
+            .temp_next
+            .chunks_mut(band_len)
+            .zip(0..)
+            .filter(|&(_, cy)| targets.iter().any(|t| t.1 == cy))
+            .map(|(band, cy)| (cy, band))
+            .collect();
+        let (temp, cells, chunks, width, height) = (&self.temp, &self.cells, &self.chunks, self.width, self.height);
+
+        par::for_each(self.threads, &mut bands, |(cy, band)| {
+            let offset = *cy as usize * band_len;
+            for &(cx, _) in targets.iter().filter(|t| t.1 == *cy) {
+                let (x0, y0, x1, y1) = chunks.bounds(cx, *cy, width, height);
+                let mut warm = false;
+                for y in y0 as usize..y1 as usize {
+                    for x in x0 as usize..x1 as usize {
+                        let i = y * w + x;
+                        let t = temp[i];
+                        let m = cells[i].material;
+                        let mut flow = 0.0;
+                        for (j, open) in [(i.wrapping_sub(w), y > 0), (i + w, y + 1 < h), (i.wrapping_sub(1), x > 0), (i + 1, x + 1 < w)] {
+                            if open {
+                                flow += table.conductance(m, cells[j].material) * (temp[j] - t);
+                            }
+                        }
+                        let mut next = t + flow * inertia[m.id() as usize];
+                        next += (AMBIENT_TEMPERATURE - next) * leak[m.id() as usize];
+                        if (next - AMBIENT_TEMPERATURE).abs() < SNAP {
+                            next = AMBIENT_TEMPERATURE;
+                        }
+                        band[i - offset] = next;
+                        warm |= next != AMBIENT_TEMPERATURE;
+                        // whole degrees are fine enough for the rules that read it
+                        if next.floor() != t.floor() {
+                            chunks.wake(x as i32, y as i32);
+                        }
+                    }
+                }
+                chunks.set_warm(cx, *cy, warm);
+            }
+        });
+
+        // only diffused chunks changed, copy those back
+        for &(cx, cy) in &targets {
+            let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+            for y in y0 as usize..y1 as usize {
+                let row = y * w + x0 as usize..y * w + x1 as usize;
+                self.temp[row.clone()].copy_from_slice(&self.temp_next[row]);
+            }
+        }
+    }
+}
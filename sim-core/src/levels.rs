@@ -0,0 +1,119 @@
+//! Liquid levels.
+//!
+//! A liquid cell only looks a few cells along its own row for somewhere to flow, so on
+//! its own water trapped under a column never pushes back up and a U-tube never levels
+//! out. After the cells have moved each tick, every connected body of one liquid that
+//! reaches an awake chunk is flood filled. If the highest cell of its surface sits above
+//! the lowest open cell beside or above the body, the weight of the column would push
+//! the liquid there, so one cell moves from the top of the surface down to that opening.
+//! Bodies joined anywhere below their surfaces settle at one level, a cell a tick.
+//!
+//! Only resting surface cells count, so a falling stream isn't lifted off the top of its
+//! pool, and nothing is pushed through a gap below the body, where it can fall anyway.
+
+use crate::{Cell, Group, Material, Simulation, props};
+
+impl Simulation {
+    /// Move a cell from the top of each body of liquid out of level to its lowest opening
+    pub(crate) fn level_liquids(&mut self) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut liquid = [false; 256];
+        for m in Material::all() {
+            liquid[m.id() as usize] = props(m).group == Group::Liquid;
+        }
+
+        let mut stack = Vec::new();
+        let (mut tops, mut openings) = (Vec::new(), Vec::new());
+        for cy in 0..self.chunks.rows {
+            for cx in 0..self.chunks.cols {
+                if !self.chunks.is_awake(cx, cy) {
+                    continue;
+                }
+                let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+                for y in y0 as usize..y1 as usize {
+                    for x in x0 as usize..x1 as usize {
+                        let i = y * w + x;
+                        let material = self.cells[i].material;
+                        if !liquid[material.id() as usize] || !self.fill(i) {
+                            continue;
+                        }
+
+                        // flood fill the body, keeping its highest resting surface cells and lowest openings
+                        stack.push((x, y));
+                        tops.clear();
+                        openings.clear();
+                        let (mut top, mut low) = (usize::MAX, 0);
+                        while let Some((x, y)) = stack.pop() {
+                            let j = y * w + x;
+                            let open_above = y > 0 && self.cells[j - w].material == Material::Empty;
+                            let resting = y + 1 == h || self.cells[j + w].material != Material::Empty;
+                            if open_above && resting && self.cells[j].vy == 0 && y <= top {
+                                if y < top {
+                                    top = y;
+                                    tops.clear();
+                                }
+                                tops.push(j);
+                            }
+                            for (nx, ny, ok) in [(x, y.wrapping_sub(1), y > 0), (x.wrapping_sub(1), y, x > 0), (x + 1, y, x + 1 < w), (x, y + 1, y + 1 < h)] {
+                                if !ok {
+                                    continue;
+                                }
+                                let n = ny * w + nx;
+                                let m = self.cells[n].material;
+                                if m == material {
+                                    if self.fill(n) {
+                                        stack.push((nx, ny));
+                                    }
+                                } else if m == Material::Empty && ny <= y && ny >= low {
+                                    if ny > low {
+                                        low = ny;
+                                        openings.clear();
+                                    }
+                                    openings.push(n);
+                                }
+                            }
+                        }
+
+                        if tops.is_empty() || openings.is_empty() || low <= top {
+                            continue;
+                        }
+                        let viscosity = props(material).viscosity;
+                        if !self.rng_next().is_multiple_of(viscosity) {
+                            continue;
+                        }
+                        let from = tops[self.rng_next() as usize % tops.len()];
+                        let to = openings[self.rng_next() as usize % openings.len()];
+                        self.level_move(from, to);
+                    }
+                }
+            }
+        }
+        self.clear_filled();
+    }
+
+    /// Move the liquid cell at `from` into the empty cell at `to`, along with its temperature,
+    /// and the air there back
+    fn level_move(&mut self, from: usize, to: usize) {
+        let w = self.width as usize;
+        let cell = self.cells[from];
+        self.cells[to] = Cell { clock: self.generation, vx: 0, vy: 0, sx: 0, sy: 0, ..cell };
+        self.cells[from] = Cell::empty_with_clock(self.generation);
+        let (warm, pressurized, depleted) = (self.temp[from] != self.temp[to], self.pressure[from] != self.pressure[to], self.oxygen[from] != self.oxygen[to]);
+        self.temp.swap(from, to);
+        self.pressure.swap(from, to);
+        self.oxygen.swap(from, to);
+        for i in [from, to] {
+            let (x, y) = ((i % w) as i32, (i / w) as i32);
+            self.chunks.wake(x, y);
+            if warm {
+                self.chunks.warm(x, y);
+            }
+            if pressurized {
+                self.chunks.pressurize(x, y);
+            }
+            if depleted {
+                self.chunks.deplete(x, y);
+            }
+        }
+    }
+}
@@ -1,9 +1,32 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-mod materials;
-pub use materials::{Group, Material, color_of, glow_of, props, update_cell};
+use chunks::Chunks;
+use wind::Wind;
 
-#[wasm_bindgen]
+mod blast;
+mod buoyancy;
+mod chunks;
+mod heat;
+mod levels;
+mod materials;
+mod oxygen;
+mod par;
+mod pressure;
+mod scene;
+mod snapshot;
+pub mod utils;
+mod wind;
+pub use heat::{AMBIENT_TEMPERATURE, heat_level};
+pub use materials::{Behaviour, CombustionProducts, Group, Material, MaterialInfo, MaterialProps, MaterialTable, MaterialsError, color_of, glow_of, load_materials, materials, props, update_cell};
+pub use oxygen::AMBIENT_OXYGEN;
+pub use scene::SceneError;
+pub use snapshot::SnapshotError;
+
+/// Seed used by `Simulation::new` on native builds, where there is no browser rng
+pub const DEFAULT_SEED: u64 = 0xA5A5_1234_89AB_CDEF;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Simulation {
     width: u32,
     height: u32,
@@ -13,100 +36,262 @@ pub struct Simulation {
     generation: u8,
     rng: u64,
     frame: u32,
-    order: Vec<usize>,
-    heat: Vec<u8>,
-    heat_next: Vec<u8>,
+    chunk_order: Vec<(u32, u32)>,
+    temp: Vec<f32>,
+    temp_next: Vec<f32>,
+    pressure: Vec<f32>,
+    pressure_next: Vec<f32>,
+    oxygen: Vec<f32>,
+    oxygen_next: Vec<f32>,
+    /// gas released into each cell during the tick, added to the pressure when it spreads
+    released: Vec<f32>,
+    /// cells a flood fill has reached during a pass, all false between passes
+    filled: Vec<bool>,
+    /// where `filled` is set, so a pass can clear just those cells again
+    filled_at: Vec<usize>,
+    wind: Wind,
+    chunks: Chunks,
+    threads: usize,
 }
 
+/// Fixed-point steps per cell of velocities and sub-cell positions
+pub const SUBCELL: i16 = 64;
+
+/// Impacts slower than this, in cells per tick, neither bounce nor pass anything on, so
+/// resting cells that gravity keeps pressing down stay put
+const IMPACT_MIN: i16 = 2 * SUBCELL;
+
+/// Share of an impact's velocity handed to a movable cell it hits, for equal densities
+const TRANSFER: f32 = 0.5;
+
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Cell {
     pub material: Material,
     pub ra: u8,
     pub rb: u8,
     pub clock: u8,
-    pub vx: i8,
-    pub vy: i8,
+    /// velocity in `SUBCELL`ths of a cell per tick
+    pub vx: i16,
+    pub vy: i16,
+    /// how far off the middle of its cell it has drifted, in `SUBCELL`ths, under half a cell
+    pub sx: i8,
+    pub sy: i8,
 }
 
 impl Cell {
     #[inline]
     pub fn empty_with_clock(clock: u8) -> Self {
-        Self { material: Material::Empty, ra: 0, rb: 0, clock, vx: 0, vy: 0 }
+        Self { material: Material::Empty, ra: 0, rb: 0, clock, vx: 0, vy: 0, sx: 0, sy: 0 }
+    }
+
+    /// Same contents, ignoring the update clock
+    #[inline]
+    fn same_state(self, other: Cell) -> bool {
+        Cell { clock: other.clock, ..self } == other
     }
 }
 
+/// Move a sub-cell position along by a velocity, returning the whole cells crossed and
+/// where it ends up in the last one
+#[inline]
+fn advance(pos: i8, v: i16) -> (i32, i8) {
+    let (sub, half) = (SUBCELL as i32, SUBCELL as i32 / 2);
+    let end = pos as i32 + v as i32;
+    let cells = (end + half).div_euclid(sub);
+    (cells, (end - cells * sub) as i8)
+}
+
 #[inline]
 fn idx(width: u32, x: i32, y: i32) -> usize {
     (y as u32 * width + x as u32) as usize
 }
 
-impl Simulation {
+/// Spread a user seed into a non-zero xorshift state (splitmix64 finaliser),
+/// so small or zero seeds still give a well mixed sequence
+fn seed_rng(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    if z == 0 { DEFAULT_SEED } else { z }
+}
+
+/// Fresh seed for `Simulation::new`: browser entropy under wasm, fixed on native
+#[cfg(feature = "wasm")]
+fn initial_seed() -> u64 {
+    // Math::random only has 53 bits of mantissa, plenty for a seed
+    (js_sys::Math::random() * (1u64 << 53) as f64) as u64
+}
+
+#[cfg(not(feature = "wasm"))]
+fn initial_seed() -> u64 {
+    DEFAULT_SEED
+}
+
+/// Advance a xorshift state, returning 32 well mixed bits
+#[inline]
+fn xorshift(state: &mut u64) -> u32 {
+    let mut x = *state;
+    x ^= x << 12;
+    x ^= x >> 25;
+    x ^= x << 27;
+    *state = x;
+    (x.wrapping_mul(2685821657736338717) >> 32) as u32
+}
+
+/// The grids as cell updates see them during a tick.
+///
+/// All chunks of one checkerboard colour are updated at once, from several threads
+/// sharing a `World`. Updates never reach more than half a chunk outside their own chunk,
+/// so those threads never touch the same cells, temperatures, pressures or oxygen; the chunk flags
+/// are atomic.
+struct World<'a> {
+    width: u32,
+    height: u32,
+    generation: u8,
+    cells: *mut Cell,
+    temp: *mut f32,
+    pressure: *mut f32,
+    oxygen: *mut f32,
+    released: *mut f32,
+    /// steady wind and gusts per wind square
+    steady_wind: &'a [[f32; 2]],
+    gusts: *mut [f32; 2],
+    wind_cols: u32,
+    chunks: &'a Chunks,
+}
+
+// SAFETY: see above, concurrent users of a World write disjoint parts of the grids
+unsafe impl Sync for World<'_> {}
+
+impl World<'_> {
     #[inline]
     fn in_bounds(&self, x: i32, y: i32) -> bool {
         x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
     }
 
     #[inline]
-    fn rng_next(&mut self) -> u32 {
-        let mut x = self.rng;
-        x ^= x << 12;
-        x ^= x >> 25;
-        x ^= x << 27;
-        self.rng = x;
-        (x.wrapping_mul(2685821657736338717) >> 32) as u32
+    fn cell(&self, i: usize) -> Cell {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: in bounds, and no other thread writes within reach of this chunk
+        unsafe { *self.cells.add(i) }
     }
 
     #[inline]
-    fn write_pixels(&mut self) {
-        let w = self.width as usize;
-        let h = self.height as usize;
+    fn put(&self, i: usize, c: Cell) {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.cells.add(i) = c }
+    }
 
-        for y in 0..h {
-            let row = y * w;
-            for x in 0..w {
-                let i = row + x;
-                let p = i * 4;
-                let cell = self.cells[i];
-                let color = color_of(cell);
-                self.pixels[p] = color[0];
-                self.pixels[p + 1] = color[1];
-                self.pixels[p + 2] = color[2];
-                self.pixels[p + 3] = color[3];
-                let glow = glow_of(cell);
-                self.glow_pixels[p] = glow[0];
-                self.glow_pixels[p + 1] = glow[1];
-                self.glow_pixels[p + 2] = glow[2];
-                self.glow_pixels[p + 3] = glow[3];
-            }
-        }
+    #[inline]
+    fn temp(&self, i: usize) -> f32 {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.temp.add(i) }
+    }
+
+    #[inline]
+    fn put_temp(&self, i: usize, t: f32) {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.temp.add(i) = t }
+    }
+
+    #[inline]
+    fn pressure(&self, i: usize) -> f32 {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.pressure.add(i) }
     }
 
-    fn diffuse_heat(&mut self) {
+    #[inline]
+    fn put_pressure(&self, i: usize, p: f32) {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.pressure.add(i) = p }
+    }
+
+    #[inline]
+    fn oxygen(&self, i: usize) -> f32 {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.oxygen.add(i) }
+    }
+
+    #[inline]
+    fn put_oxygen(&self, i: usize, o: f32) {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.oxygen.add(i) = o }
+    }
+
+    #[inline]
+    fn release(&self, i: usize, amount: f32) {
+        debug_assert!(i < (self.width * self.height) as usize);
+        // SAFETY: as for `cell`
+        unsafe { *self.released.add(i) += amount }
+    }
+
+    #[inline]
+    fn wind_square(&self, x: i32, y: i32) -> usize {
+        ((y as u32 / wind::WIND_CELL) * self.wind_cols + x as u32 / wind::WIND_CELL) as usize
+    }
+
+    /// Swap the temperatures, pressures and oxygen of two cells whose contents just swapped.
+    /// The temperature belongs to the material; the pressure and oxygen to the air it pushed
+    /// aside.
+    #[inline]
+    fn carry(&self, a: usize, b: usize) {
         let w = self.width as usize;
-        let h = self.height as usize;
-        let len = w * h;
-        for i in 0..len {
-            let x = i % w;
-            let y = i / w;
-            let n = if y > 0 { self.heat[i - w] as u32 } else { 0 };
-            let s = if y + 1 < h { self.heat[i + w] as u32 } else { 0 };
-            let e = if x + 1 < w { self.heat[i + 1] as u32 } else { 0 };
-            let ww = if x > 0 { self.heat[i - 1] as u32 } else { 0 };
-            let c = self.heat[i] as u32;
-            // Equal-weight 5-tap average, no decay — heat fills enclosed spaces naturally
-            let diffused = ((c + n + s + e + ww) / 5) as u8;
-            self.heat_next[i] = diffused.max(self.heat_next[i]);
+        let (ta, tb) = (self.temp(a), self.temp(b));
+        if ta != tb {
+            self.put_temp(a, tb);
+            self.put_temp(b, ta);
+            self.chunks.warm((a % w) as i32, (a / w) as i32);
+            self.chunks.warm((b % w) as i32, (b / w) as i32);
+        }
+        let (pa, pb) = (self.pressure(a), self.pressure(b));
+        if pa != pb {
+            self.put_pressure(a, pb);
+            self.put_pressure(b, pa);
+            self.chunks.pressurize((a % w) as i32, (a / w) as i32);
+            self.chunks.pressurize((b % w) as i32, (b / w) as i32);
         }
+        let (oa, ob) = (self.oxygen(a), self.oxygen(b));
+        if oa != ob {
+            self.put_oxygen(a, ob);
+            self.put_oxygen(b, oa);
+            self.chunks.deplete((a % w) as i32, (a / w) as i32);
+            self.chunks.deplete((b % w) as i32, (b / w) as i32);
+        }
+    }
 
-        std::mem::swap(&mut self.heat, &mut self.heat_next);
-        self.heat_next.fill(0); // clear for next tick's source writes
+    /// Update every cell of a chunk, row by row. The vertical direction is picked per
+    /// tick and each row draws its own horizontal direction, so neither side is favoured.
+    fn update_chunk(&self, cx: u32, cy: u32, rng: &mut u64, bottom_up: bool) {
+        let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+        // one bit per row, a chunk is at most 32 rows tall
+        let row_dirs = xorshift(rng);
+        for r in 0..y1 - y0 {
+            let y = if bottom_up { y1 - 1 - r } else { y0 + r } as i32;
+            if (row_dirs >> r) & 1 == 0 {
+                for x in x0..x1 {
+                    self.update_at(x as i32, y, rng);
+                }
+            } else {
+                for x in (x0..x1).rev() {
+                    self.update_at(x as i32, y, rng);
+                }
+            }
+        }
     }
 
     #[inline]
-    fn update_at(&mut self, x: i32, y: i32) {
+    fn update_at(&self, x: i32, y: i32, rng: &mut u64) {
         let i = idx(self.width, x, y);
-        let cell = self.cells[i];
+        let cell = self.cell(i);
 
         // skip cells already update this tick
         if cell.clock.wrapping_sub(self.generation) == 0 {
@@ -118,15 +303,171 @@ impl Simulation {
             return;
         }
 
-        let api = SimAPI { x, y, sim: self };
+        let api = SimAPI { x, y, world: self, rng };
         update_cell(cell, api);
+
+        // in-place changes (lifetimes, velocity) keep the chunk awake; moves and
+        // neighbour writes already woke it through `set`
+        if !self.cell(i).same_state(cell) {
+            self.chunks.wake(x, y);
+        }
+    }
+}
+
+impl Simulation {
+    #[inline]
+    fn rng_next(&mut self) -> u32 {
+        xorshift(&mut self.rng)
+    }
+
+    /// Mark cell `i` as reached by a flood fill, returning false if it already was
+    #[inline]
+    fn fill(&mut self, i: usize) -> bool {
+        if self.filled[i] {
+            return false;
+        }
+        self.filled[i] = true;
+        self.filled_at.push(i);
+        true
+    }
+
+    /// Forget every cell the flood fills of a pass reached
+    fn clear_filled(&mut self) {
+        for i in self.filled_at.drain(..) {
+            self.filled[i] = false;
+        }
+    }
+
+    /// Put a fresh cell of `material` at (x, y), marked as updated this tick. Materials that
+    /// hold or start at a temperature arrive at it, so painted ice doesn't start out melting.
+    fn paint(&mut self, x: i32, y: i32, material: Material) {
+        let i = idx(self.width, x, y);
+        self.cells[i] = Cell { material, ra: self.rng_next() as u8, rb: 0, clock: self.generation, vx: 0, vy: 0, sx: 0, sy: 0 };
+        let p = props(material);
+        if let Some(t) = p.source_temperature.or(p.initial_temperature) {
+            self.temp[i] = t;
+            self.chunks.warm(x, y);
+        }
+        self.chunks.wake(x, y);
+    }
+
+    /// Write pixels for chunks touched since the last write
+    fn write_pixels(&mut self) {
+        let w = self.width as usize;
+
+        for cy in 0..self.chunks.rows {
+            for cx in 0..self.chunks.cols {
+                if self.chunks.take_dirty(cx, cy) {
+                    let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+                    for y in y0 as usize..y1 as usize {
+                        self.write_pixel_row(y * w + x0 as usize, y * w + x1 as usize);
+                    }
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn write_pixel_row(&mut self, start: usize, end: usize) {
+        for i in start..end {
+            let p = i * 4;
+            let cell = self.cells[i];
+            let color = color_of(cell);
+            self.pixels[p] = color[0];
+            self.pixels[p + 1] = color[1];
+            self.pixels[p + 2] = color[2];
+            self.pixels[p + 3] = color[3];
+            let glow = glow_of(cell);
+            self.glow_pixels[p] = glow[0];
+            self.glow_pixels[p + 1] = glow[1];
+            self.glow_pixels[p + 2] = glow[2];
+            self.glow_pixels[p + 3] = glow[3];
+        }
+    }
+}
+
+impl Simulation {
+    /// Spread `step` over up to `threads` threads. The state after each step is the same
+    /// whatever the thread count, only the wall time changes.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Cell grid, row-major
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Temperature of every cell in degrees Celsius, row-major
+    pub fn temperatures(&self) -> &[f32] {
+        &self.temp
+    }
+
+    /// Pressure of every cell over the surrounding air's, row-major; 0 in powders and solids
+    pub fn pressures(&self) -> &[f32] {
+        &self.pressure
+    }
+
+    /// Oxygen in the air of every cell as a share of fresh air's, row-major; 1 in powders,
+    /// liquids and solids
+    pub fn oxygen(&self) -> &[f32] {
+        &self.oxygen
+    }
+
+    /// Set the pressure of every cell in a rectangle, corners inclusive and clipped to the
+    /// grid, so tests can start from a pressure difference instead of building one up
+    #[doc(hidden)]
+    pub fn set_pressure_rect(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, p: f32) {
+        let (x0, x1) = (x0.min(x1), x0.max(x1).min(self.width.saturating_sub(1)));
+        let (y0, y1) = (y0.min(y1), y0.max(y1).min(self.height.saturating_sub(1)));
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                self.pressure[idx(self.width, x as i32, y as i32)] = p;
+                self.chunks.pressurize(x as i32, y as i32);
+                self.chunks.wake(x as i32, y as i32);
+            }
+        }
+    }
+
+    /// Set the velocity of the cell at (x, y) in `SUBCELL`ths of a cell per tick, so tests
+    /// can set things moving that nothing in the grid would push
+    #[doc(hidden)]
+    pub fn set_velocity(&mut self, x: u32, y: u32, vx: i16, vy: i16) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let i = idx(self.width, x as i32, y as i32);
+        self.cells[i] = Cell { vx, vy, ..self.cells[i] };
+        self.chunks.wake(x as i32, y as i32);
+    }
+
+    /// Wind over cell (x, y), steady and gusts together, as [vx, vy] in cells per tick
+    pub fn wind_at(&self, x: u32, y: u32) -> [f32; 2] {
+        if self.cells.is_empty() {
+            return [0.0; 2];
+        }
+        let i = self.wind.square(x.min(self.width - 1) as i32, y.min(self.height - 1) as i32);
+        let (s, g) = (self.wind.steady[i], self.wind.gusts[i]);
+        [s[0] + g[0], s[1] + g[1]]
+    }
+
+    /// RGBA pixels written at the end of each `step`
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// RGBA glow layer written alongside `pixels`
+    pub fn glow_pixels(&self) -> &[u8] {
+        &self.glow_pixels
     }
 }
 
 pub struct SimAPI<'a> {
     pub x: i32,
     pub y: i32,
-    pub sim: &'a mut Simulation,
+    world: &'a World<'a>,
+    rng: &'a mut u64,
 }
 
 impl<'a> SimAPI<'a> {
@@ -135,11 +476,11 @@ impl<'a> SimAPI<'a> {
         let nx = self.x + dx;
         let ny = self.y + dy;
 
-        if !self.sim.in_bounds(nx, ny) {
-            return Cell { material: Material::Wall, ra: 0, rb: 0, clock: self.sim.generation, vx: 0, vy: 0 };
+        if !self.world.in_bounds(nx, ny) {
+            return Cell { material: Material::Wall, ra: 0, rb: 0, clock: self.world.generation, vx: 0, vy: 0, sx: 0, sy: 0 };
         }
 
-        self.sim.cells[idx(self.sim.width, nx, ny)]
+        self.world.cell(idx(self.world.width, nx, ny))
     }
 
     #[inline]
@@ -147,20 +488,24 @@ impl<'a> SimAPI<'a> {
         let nx = self.x + dx;
         let ny = self.y + dy;
 
-        if !self.sim.in_bounds(nx, ny) {
+        if !self.world.in_bounds(nx, ny) {
             return;
         }
 
-        let di = idx(self.sim.width, nx, ny);
-        v.clock = self.sim.generation.wrapping_add(1);
-        self.sim.cells[di] = v;
+        let di = idx(self.world.width, nx, ny);
+        v.clock = self.world.generation;
+        if !self.world.cell(di).same_state(v) {
+            self.world.chunks.wake(nx, ny);
+        }
+        self.world.put(di, v);
     }
 
     #[inline]
     pub fn clear_here(&mut self) {
-        let i = idx(self.sim.width, self.x, self.y);
+        let i = idx(self.world.width, self.x, self.y);
         // mark cell as empty and updated
-        self.sim.cells[i] = Cell::empty_with_clock(self.sim.generation.wrapping_add(1));
+        self.world.put(i, Cell::empty_with_clock(self.world.generation));
+        self.world.chunks.wake(self.x, self.y);
     }
 
     /// Move cell into target if Empty
@@ -170,6 +515,7 @@ impl<'a> SimAPI<'a> {
         if self.get(dx, dy).material == Material::Empty {
             self.set(dx, dy, cell);
             self.clear_here();
+            self.carry(dx, dy);
             true
         } else {
             false
@@ -180,69 +526,99 @@ impl<'a> SimAPI<'a> {
     /// Clears current cell if successful
     #[inline]
     pub fn try_move_into(&mut self, dx: i32, dy: i32, cell: Cell, allowed_materials: &[Material]) -> bool {
-        let target = self.get(dx, dy);
+        let allowed = allowed_materials.contains(&self.get(dx, dy).material);
+        if allowed {
+            self.swap_into(dx, dy, cell);
+        }
+        allowed
+    }
 
-        // Check if target material is in the allowed list
-        if allowed_materials.contains(&target.material) {
-            // Store the target cell to put in current position
-            let mut target_cell = target;
-            target_cell.clock = self.sim.generation.wrapping_add(1);
+    /// Move cell into target if what is there gives way to it, by `MaterialProps::displaces`
+    /// Whatever was there takes its place
+    #[inline]
+    pub fn try_displace(&mut self, dx: i32, dy: i32, cell: Cell) -> bool {
+        let displaces = props(cell.material).displaces(props(self.get(dx, dy).material), dy);
+        if displaces {
+            self.swap_into(dx, dy, cell);
+        }
+        displaces
+    }
 
-            // Move our cell to target position
-            self.set(dx, dy, cell);
+    /// Put cell at the target and the target's cell here
+    #[inline]
+    fn swap_into(&mut self, dx: i32, dy: i32, cell: Cell) {
+        // Store the target cell to put in current position
+        let mut target_cell = self.get(dx, dy);
+        target_cell.clock = self.world.generation;
+
+        // Move our cell to target position
+        self.set(dx, dy, cell);
+
+        // Put target cell in current position
+        let i = idx(self.world.width, self.x, self.y);
+        self.world.put(i, target_cell);
+        self.world.chunks.wake(self.x, self.y);
+        self.carry(dx, dy);
+    }
 
-            // Put target cell in current position
-            let i = idx(self.sim.width, self.x, self.y);
-            self.sim.cells[i] = target_cell;
+    /// Move the cell at offset a into offset b, which must be empty, along with its temperature
+    #[inline]
+    pub fn move_cell(&mut self, ax: i32, ay: i32, bx: i32, by: i32) {
+        let (a, b) = (idx(self.world.width, self.x + ax, self.y + ay), idx(self.world.width, self.x + bx, self.y + by));
+        let cell = self.world.cell(a);
+        self.set(bx, by, cell);
+        self.world.put(a, Cell::empty_with_clock(self.world.generation));
+        self.world.chunks.wake(self.x + ax, self.y + ay);
+        self.world.carry(a, b);
+    }
 
-            true
-        } else {
-            false
-        }
+    /// Whether the cell at the offset is on the grid; off-grid cells read as wall
+    #[inline]
+    pub fn in_bounds(&self, dx: i32, dy: i32) -> bool {
+        self.world.in_bounds(self.x + dx, self.y + dy)
     }
 
-    /// Move into target if Empty, or dissolve it if it matches a (Material, rate) pair.
-    /// Rate is the 1/N probability denominator - use 1 for always. Returns false if
-    /// matched but the rate check failed (treated as blocked).
+    /// Update this cell's chunk next tick even if nothing changes, for cells waiting on chance
     #[inline]
-    pub fn try_move_dissolving(&mut self, dx: i32, dy: i32, cell: Cell, dissolve: &[(Material, u32)]) -> bool {
+    pub fn keep_awake(&self) {
+        self.world.chunks.wake(self.x, self.y);
+    }
+
+    /// Swap temperature, pressure and oxygen with the cell at the offset, after swapping contents
+    /// with it. Needed by any move that doesn't go through `try_move`, `try_move_into`, `try_displace` or velocity.
+    #[inline]
+    pub fn carry(&mut self, dx: i32, dy: i32) {
         let nx = self.x + dx;
         let ny = self.y + dy;
-        if !self.sim.in_bounds(nx, ny) {
-            return false;
-        }
-        let target = self.get(dx, dy).material;
-        for &(mat, rate) in dissolve {
-            if target == mat {
-                if rate == 1 || self.rand_u32() % rate == 0 {
-                    self.set(dx, dy, cell);
-                    self.clear_here();
-                    return true;
-                }
-                return false;
-            }
+        if self.world.in_bounds(nx, ny) {
+            self.world.carry(idx(self.world.width, self.x, self.y), idx(self.world.width, nx, ny));
         }
-        self.try_move(dx, dy, cell)
     }
 
+    /// Add gravity, the push of any pressure difference across the cell and the wind to its velocity
     pub fn apply_gravity(&mut self) {
-        let i = idx(self.sim.width, self.x, self.y);
-        let cell = self.sim.cells[i];
+        let i = idx(self.world.width, self.x, self.y);
+        let cell = self.world.cell(i);
         let p = props(cell.material);
-        if p.gravity == 0 {
+        let (px, py) = if p.group != Group::Solid { pressure::push(self) } else { (0, 0) };
+        let (wx, wy) = if p.wind_response > 0.0 { wind::catch(self, p.wind_response) } else { (0, 0) };
+        let gravity = (p.gravity * SUBCELL as f32) as i16;
+        let (fx, fy) = (px.saturating_add(wx), py.saturating_add(wy));
+        if gravity == 0 && fx == 0 && fy == 0 {
             return;
         }
 
-        // add gravity to vy, clamp to terminal velocity
-        let tv = p.terminal_velocity as i16;
-        let new_vy = (cell.vy as i16 + p.gravity as i16).clamp(-tv, tv) as i8;
-        // write directly to grid so vy accumulates even when resolve is skipped
-        self.sim.cells[i].vy = new_vy;
+        // add the forces, clamp to terminal velocity
+        let tv = p.terminal_velocity as i16 * SUBCELL;
+        let new_vy = cell.vy.saturating_add(gravity).saturating_add(fy).clamp(-tv, tv);
+        let new_vx = if fx != 0 { cell.vx.saturating_add(fx).clamp(-tv, tv) } else { cell.vx };
+        // write directly to grid so velocity accumulates even when resolve is skipped
+        self.world.put(i, Cell { vx: new_vx, vy: new_vy, ..cell });
     }
 
     pub fn resolve_velocity(&mut self) {
-        let i = idx(self.sim.width, self.x, self.y);
-        let mut cell = self.sim.cells[i];
+        let i = idx(self.world.width, self.x, self.y);
+        let mut cell = self.world.cell(i);
         if cell.vx == 0 && cell.vy == 0 {
             return;
         }
@@ -250,9 +626,10 @@ impl<'a> SimAPI<'a> {
         // remember start pos
         let orig_x = self.x;
         let orig_y = self.y;
-        // remaining distance to travel this tick
-        let mut remaining_x = cell.vx as i32;
-        let mut remaining_y = cell.vy as i32;
+        // advance the position within the cell; each half cell it passes takes it to the next
+        let (mut remaining_x, sx) = advance(cell.sx, cell.vx);
+        let (mut remaining_y, sy) = advance(cell.sy, cell.vy);
+        (cell.sx, cell.sy) = (sx, sy);
 
         // step one cell at a time along the velocity vector
         while remaining_x != 0 || remaining_y != 0 {
@@ -279,18 +656,18 @@ impl<'a> SimAPI<'a> {
 
             // 0 velocity on whichver axis is blocked
             if v_blocked {
-                cell.vy = 0;
+                (cell.vy, cell.sy) = (self.collide(cell.material, 0, step_y, cell.vy, self.y - orig_y), 0);
                 remaining_y = 0;
             }
             if h_blocked {
-                cell.vx = 0;
+                (cell.vx, cell.sx) = (self.collide(cell.material, step_x, 0, cell.vx, self.x - orig_x), 0);
                 remaining_x = 0;
             }
 
             // cornr case: diagonal blocked but both axes are open
             // prefer vertical movement, discard horizontal
             if !v_blocked && !h_blocked {
-                cell.vx = 0;
+                (cell.vx, cell.sx) = (0, 0);
                 remaining_x = 0;
             }
 
@@ -303,8 +680,10 @@ impl<'a> SimAPI<'a> {
         if self.x != orig_x || self.y != orig_y {
             // set() sets the clock, no double processing this tick
             self.set(0, 0, cell);
-            let oi = idx(self.sim.width, orig_x, orig_y);
-            self.sim.cells[oi] = Cell::empty_with_clock(self.sim.generation.wrapping_add(1));
+            let oi = idx(self.world.width, orig_x, orig_y);
+            self.world.put(oi, Cell::empty_with_clock(self.world.generation));
+            self.world.chunks.wake(orig_x, orig_y);
+            self.world.carry(oi, idx(self.world.width, self.x, self.y));
         }
 
         // apply drag/friction to horizontal velocity
@@ -313,87 +692,271 @@ impl<'a> SimAPI<'a> {
         let new_vx = if cell.vx != 0 {
             if below.material == Material::Empty {
                 // airborne, gentle slowdown
-                (cell.vx as f32 * p.air_drag) as i8
+                (cell.vx as f32 * p.air_drag) as i16
             } else {
                 // on a surface, combine surface slipperiness with material friction
                 let sp = props(below.material);
                 let combined = sp.surface_slipperiness * p.base_friction;
-                (cell.vx as f32 * combined) as i8
+                (cell.vx as f32 * combined) as i16
             }
         } else {
             0
         };
 
         // write final velocity back to grid
-        let fi = idx(self.sim.width, self.x, self.y);
-        self.sim.cells[fi].vx = new_vx;
-        self.sim.cells[fi].vy = cell.vy;
+        let fi = idx(self.world.width, self.x, self.y);
+        let moved = self.world.cell(fi);
+        self.world.put(fi, Cell { vx: new_vx, vy: cell.vy, sx: cell.sx, sy: cell.sy, ..moved });
+    }
+
+    /// A cell of `material` moving at `v` along one axis ran into the cell at the offset.
+    /// A movable cell there takes part of the momentum, knocked on and to a random side, or
+    /// back out if it's packed in, and the mover bounces back by its restitution. Returns the mover's velocity on that axis.
+    /// `travelled` is how far the mover has come along that axis this tick.
+    fn collide(&mut self, material: Material, dx: i32, dy: i32, v: i16, travelled: i32) -> i16 {
+        let target = self.get(dx, dy);
+        // what the mover has over the cell it caught up with is what hits
+        let ahead = if dx != 0 { target.vx } else { target.vy };
+        let ahead = if ahead.signum() == v.signum() { ahead } else { 0 };
+        let impact = v - ahead;
+        // pulling away faster than the mover comes on, nothing hits
+        if impact.signum() != v.signum() {
+            return v;
+        }
+        if impact.abs() < IMPACT_MIN {
+            return ahead;
+        }
+
+        let p = props(material);
+        let tp = props(target.material);
+        if matches!(tp.group, Group::Powder | Group::Liquid) && self.in_bounds(dx, dy) {
+            let tv = tp.terminal_velocity as i16 * SUBCELL;
+            let kick = (impact as f32 * TRANSFER * (p.density / tp.density).min(1.0)) as i16;
+            // packed in behind, it splashes back out instead; the cell behind is only looked at
+            // while it is within reach, past that it counts as open
+            let behind_in_reach = travelled.unsigned_abs() + 2 <= chunks::MAX_REACH;
+            let kick = if !behind_in_reach || self.get(2 * dx, 2 * dy).material == Material::Empty {
+                kick
+            } else {
+                -kick / 2
+            };
+            let side = if self.rand_u32() & 1 == 0 { kick.abs() / 2 } else { -kick.abs() / 2 };
+            let (kx, ky) = if dx != 0 { (kick, side) } else { (side, kick) };
+            let vx = target.vx.saturating_add(kx).clamp(-tv, tv);
+            let vy = target.vy.saturating_add(ky).clamp(-tv, tv);
+            self.set(dx, dy, Cell { vx, vy, ..target });
+        }
+        ahead - (impact as f32 * p.restitution) as i16
     }
 
     #[inline]
     pub fn rb(&self) -> u8 {
-        self.sim.cells[idx(self.sim.width, self.x, self.y)].rb
+        self.world.cell(idx(self.world.width, self.x, self.y)).rb
     }
 
     #[inline]
     pub fn set_rb(&mut self, v: u8) {
-        self.sim.cells[idx(self.sim.width, self.x, self.y)].rb = v;
+        let i = idx(self.world.width, self.x, self.y);
+        self.world.put(i, Cell { rb: v, ..self.world.cell(i) });
     }
 
     #[inline]
     pub fn rand_u32(&mut self) -> u32 {
-        self.sim.rng_next()
+        xorshift(self.rng)
+    }
+
+    /// True with a 1-in-`n` chance. Never for 0, always for 1.
+    #[inline]
+    pub fn one_in(&mut self, n: u32) -> bool {
+        n != 0 && (n == 1 || self.rand_u32().is_multiple_of(n))
     }
 
     #[inline]
     pub fn generation(&self) -> u8 {
-        self.sim.generation
+        self.world.generation
     }
 
+    /// Temperature of this cell in degrees Celsius
     #[inline]
-    pub fn heat_here(&self) -> u8 {
-        self.sim.heat[idx(self.sim.width, self.x, self.y)]
+    pub fn temp_here(&self) -> f32 {
+        self.world.temp(idx(self.world.width, self.x, self.y))
     }
 
     #[inline]
-    pub fn get_heat(&self, dx: i32, dy: i32) -> u8 {
+    pub fn get_temp(&self, dx: i32, dy: i32) -> f32 {
         let nx = self.x + dx;
         let ny = self.y + dy;
-        if !self.sim.in_bounds(nx, ny) {
-            return 0;
+        if !self.world.in_bounds(nx, ny) {
+            return AMBIENT_TEMPERATURE;
         }
-        self.sim.heat[idx(self.sim.width, nx, ny)]
+        self.world.temp(idx(self.world.width, nx, ny))
     }
 
+    /// Add heat to the cell at the offset, raising its temperature by `energy` over its
+    /// heat capacity. Negative takes heat away.
     #[inline]
-    pub fn set_heat(&mut self, dx: i32, dy: i32, v: u8) {
+    pub fn add_heat(&mut self, dx: i32, dy: i32, energy: f32) {
         let nx = self.x + dx;
         let ny = self.y + dy;
-        if !self.sim.in_bounds(nx, ny) {
+        if !self.world.in_bounds(nx, ny) {
             return;
         }
-        self.sim.heat_next[idx(self.sim.width, nx, ny)] = v;
+        let i = idx(self.world.width, nx, ny);
+        let t = self.world.temp(i);
+        let next = t + energy / props(self.world.cell(i).material).heat_capacity;
+        self.world.put_temp(i, next);
+        self.world.chunks.warm(nx, ny);
+        // like diffusion, whole degrees are what rules look at
+        if next.floor() != t.floor() {
+            self.world.chunks.wake(nx, ny);
+        }
+    }
+
+    #[inline]
+    pub fn set_temp(&mut self, dx: i32, dy: i32, t: f32) {
+        let nx = self.x + dx;
+        let ny = self.y + dy;
+        if !self.world.in_bounds(nx, ny) {
+            return;
+        }
+        self.world.put_temp(idx(self.world.width, nx, ny), t);
+        self.world.chunks.warm(nx, ny);
+    }
+
+    /// Pressure at the offset over the surrounding air's; 0 off the grid, which opens onto it
+    #[inline]
+    pub fn get_pressure(&self, dx: i32, dy: i32) -> f32 {
+        let nx = self.x + dx;
+        let ny = self.y + dy;
+        if !self.world.in_bounds(nx, ny) {
+            return 0.0;
+        }
+        self.world.pressure(idx(self.world.width, nx, ny))
+    }
+
+    /// Release gas at the offset, raising the pressure of the fluid cells there and right
+    /// around it, so it doesn't start out as a one-cell spike. It takes effect at the end of
+    /// the tick, when pressure spreads.
+    pub fn add_pressure(&mut self, dx: i32, dy: i32, amount: f32) {
+        let mut fluid = [(0, 0); 9];
+        let mut n = 0;
+        for ay in dy - 1..=dy + 1 {
+            for ax in dx - 1..=dx + 1 {
+                if self.in_bounds(ax, ay) && pressure::holds_pressure(self.get(ax, ay).material) {
+                    fluid[n] = (ax, ay);
+                    n += 1;
+                }
+            }
+        }
+        for &(ax, ay) in &fluid[..n] {
+            let (nx, ny) = (self.x + ax, self.y + ay);
+            let i = idx(self.world.width, nx, ny);
+            self.world.release(i, amount / n as f32);
+            self.world.chunks.pressurize(nx, ny);
+        }
+    }
+
+    /// Oxygen in the air at the offset, as a share of fresh air's; fresh off the grid, which
+    /// opens onto the outside
+    #[inline]
+    pub fn get_oxygen(&self, dx: i32, dy: i32) -> f32 {
+        let nx = self.x + dx;
+        let ny = self.y + dy;
+        if !self.world.in_bounds(nx, ny) {
+            return AMBIENT_OXYGEN;
+        }
+        self.world.oxygen(idx(self.world.width, nx, ny))
+    }
+
+    /// Burn up to `amount` of the oxygen in the air at the offset
+    #[inline]
+    pub fn use_oxygen(&mut self, dx: i32, dy: i32, amount: f32) {
+        let nx = self.x + dx;
+        let ny = self.y + dy;
+        if !self.world.in_bounds(nx, ny) {
+            return;
+        }
+        let i = idx(self.world.width, nx, ny);
+        self.world.put_oxygen(i, (self.world.oxygen(i) - amount).max(0.0));
+        self.world.chunks.deplete(nx, ny);
+    }
+
+    /// Wind blowing over this cell, steady and gusts together, in cells per tick
+    #[inline]
+    pub fn wind_here(&self) -> [f32; 2] {
+        let i = self.world.wind_square(self.x, self.y);
+        // SAFETY: as for `World::cell`; gusts fed from chunks updating at once land in different squares
+        let gust = unsafe { *self.world.gusts.add(i) };
+        let steady = self.world.steady_wind[i];
+        [steady[0] + gust[0], steady[1] + gust[1]]
+    }
+
+    /// Feed a gust into the wind over the cell at the offset, for things that blow
+    #[inline]
+    pub fn add_wind(&mut self, dx: i32, dy: i32, vx: f32, vy: f32) {
+        let nx = self.x + dx;
+        let ny = self.y + dy;
+        if !self.world.in_bounds(nx, ny) {
+            return;
+        }
+        let i = self.world.wind_square(nx, ny);
+        // SAFETY: as for `wind_here`
+        unsafe {
+            let g = &mut *self.world.gusts.add(i);
+            g[0] += vx;
+            g[1] += vy;
+        }
+        self.world.chunks.wake(nx, ny);
+    }
+
+    /// Whether there may be pressure around this cell, so pressure checks can skip still air
+    #[inline]
+    pub fn pressurized(&self) -> bool {
+        self.world.chunks.pressurized_near(self.x, self.y)
     }
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Simulation {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(width: u32, height: u32) -> Simulation {
-        let len = (width * height) as usize;
+        #[cfg(feature = "wasm")]
+        console_error_panic_hook::set_once();
+        Self::with_seed(width, height, initial_seed())
+    }
+
+    /// Create a simulation where the rng and every per-cell random draw come from `seed`,
+    /// so the same seed and the same inputs always step to the same state. Panics if the
+    /// grid has more cells than a u32 counts, as scenes and saves already refuse.
+    pub fn with_seed(width: u32, height: u32, seed: u64) -> Simulation {
+        let len = width.checked_mul(height).expect("grid has more cells than a u32 counts") as usize;
         let mut sim = Simulation {
             width,
             height,
-            cells: vec![Cell { material: Material::Empty, ra: 100 + (js_sys::Math::random() * 50.0) as u8, rb: 0, clock: 0, vx: 0, vy: 0 }; len],
+            cells: Vec::with_capacity(len),
             pixels: vec![0; len * 4],
             glow_pixels: vec![0; len * 4],
             generation: 0,
-            rng: 0xA5A5_1234_89AB_CDEF,
+            rng: seed_rng(seed),
             frame: 0,
-            order: (0..len).collect(),
-            heat: vec![0; len],
-            heat_next: vec![0; len],
+            chunk_order: Vec::new(),
+            temp: vec![AMBIENT_TEMPERATURE; len],
+            temp_next: vec![AMBIENT_TEMPERATURE; len],
+            pressure: vec![0.0; len],
+            pressure_next: vec![0.0; len],
+            oxygen: vec![AMBIENT_OXYGEN; len],
+            oxygen_next: vec![AMBIENT_OXYGEN; len],
+            released: vec![0.0; len],
+            filled: vec![false; len],
+            filled_at: Vec::new(),
+            wind: Wind::new(width, height),
+            chunks: Chunks::new(width, height),
+            threads: 1,
         };
+        for _ in 0..len {
+            let ra = 100 + (sim.rng_next() % 50) as u8;
+            sim.cells.push(Cell { material: Material::Empty, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+        }
         sim.write_pixels();
         sim
     }
@@ -406,48 +969,65 @@ impl Simulation {
         self.height
     }
 
-    pub fn pixels_ptr(&self) -> *const u8 {
-        self.pixels.as_ptr()
-    }
-
-    pub fn pixels_len(&self) -> usize {
-        self.pixels.len()
-    }
-
-    pub fn glow_pixels_ptr(&self) -> *const u8 {
-        self.glow_pixels.as_ptr()
-    }
-
-    pub fn glow_pixels_len(&self) -> usize {
-        self.glow_pixels.len()
-    }
-
-    pub fn heat_ptr(&self) -> *const u8 {
-        self.heat.as_ptr()
-    }
-
-    pub fn heat_len(&self) -> usize {
-        self.heat.len()
-    }
-
     /// Step the simulation 'ticks' amount of steps
     pub fn step(&mut self, ticks: u32) {
+        // a grid with no cells has nothing to update, and the passes can't split it into bands
+        if self.cells.is_empty() {
+            self.frame += 1;
+            return;
+        }
         for _ in 0..ticks {
             self.generation = self.generation.wrapping_add(1);
+            self.chunks.advance();
 
-            let w = self.width as usize;
-
-            for i in (1..self.order.len()).rev() {
+            // chunks are updated one checkerboard colour at a time; chunks of the same colour
+            // are two apart and never touch the same cells, so each colour can run in parallel
+            let mut phases = [(0, 0), (1, 0), (0, 1), (1, 1)];
+            for i in (1..phases.len()).rev() {
                 let j = (self.rng_next() as usize) % (i + 1);
-                self.order.swap(i, j);
+                phases.swap(i, j);
             }
-
-            for idx in 0..self.order.len() {
-                let i = self.order[idx];
-                self.update_at((i % w) as i32, (i / w) as i32);
+            // every chunk gets its own rng stream, so the result doesn't depend on which
+            // thread updates it, or how many there are
+            let tick_key = (self.rng_next() as u64) << 32 | self.rng_next() as u64;
+            let bottom_up = self.generation & 1 == 0;
+
+            let world = World {
+                width: self.width,
+                height: self.height,
+                generation: self.generation,
+                cells: self.cells.as_mut_ptr(),
+                temp: self.temp.as_mut_ptr(),
+                pressure: self.pressure.as_mut_ptr(),
+                oxygen: self.oxygen.as_mut_ptr(),
+                released: self.released.as_mut_ptr(),
+                steady_wind: &self.wind.steady,
+                gusts: self.wind.gusts.as_mut_ptr(),
+                wind_cols: self.wind.cols,
+                chunks: &self.chunks,
+            };
+            for (px, py) in phases {
+                // only chunks that something woke last tick are visited
+                self.chunk_order.clear();
+                for cy in (py..self.chunks.rows).step_by(2) {
+                    for cx in (px..self.chunks.cols).step_by(2) {
+                        if self.chunks.is_awake(cx, cy) {
+                            self.chunk_order.push((cx, cy));
+                        }
+                    }
+                }
+                par::for_each(self.threads, &mut self.chunk_order, |&mut (cx, cy)| {
+                    let mut rng = seed_rng(tick_key ^ ((cy * world.chunks.cols + cx) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+                    world.update_chunk(cx, cy, &mut rng, bottom_up);
+                });
             }
 
+            self.level_liquids();
+            self.float_solids();
             self.diffuse_heat();
+            self.diffuse_pressure();
+            self.diffuse_oxygen();
+            self.blow();
         }
         self.frame += 1;
         self.write_pixels();
@@ -465,10 +1045,7 @@ impl Simulation {
         }
 
         let i = idx(self.width, x as i32, y as i32);
-        let material = Material::from_id(material_id);
-
-        // mark updated
-        self.cells[i] = Cell { material, ra: self.rng_next() as u8, rb: 0, clock: self.generation.wrapping_add(1), vx: 0, vy: 0 };
+        self.paint(x as i32, y as i32, Material::from_id(material_id));
 
         let p = i * 4;
         let c = color_of(self.cells[i]);
@@ -480,32 +1057,137 @@ impl Simulation {
 
     /// Paint a filled circle
     pub fn paint_circle(&mut self, cx: u32, cy: u32, radius: u32, material_id: u8) {
-        let r = radius as i32;
         let m = Material::from_id(material_id);
-        let cx = cx as i32;
-        let cy = cy as i32;
-        let r2 = r * r;
-
-        for dy in -r..=r {
-            for dx in -r..=r {
+        let (cx, cy, r) = (cx as i64, cy as i64, radius as i64);
+        // squares of distances across the whole u32 range only fit an i128
+        let r2 = (r as i128).pow(2);
+
+        // only the part of its bounding box on the grid
+        for y in (cy - r).max(0)..=(cy + r).min(self.height as i64 - 1) {
+            for x in (cx - r).max(0)..=(cx + r).min(self.width as i64 - 1) {
+                let (dx, dy) = ((x - cx) as i128, (y - cy) as i128);
                 if dx * dx + dy * dy <= r2 {
-                    let x = cx + dx;
-                    let y = cy + dy;
-                    if self.in_bounds(x, y) {
-                        let i = idx(self.width, x, y);
-                        self.cells[i] = Cell { material: m, ra: self.rng_next() as u8, rb: 0, clock: self.generation.wrapping_add(1), vx: 0, vy: 0 };
-                    }
+                    self.paint(x as i32, y as i32, m);
                 }
             }
         }
         self.write_pixels();
     }
 
+    /// Paint a filled rectangle, corners inclusive and clipped to the grid
+    pub fn paint_rect(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, material_id: u8) {
+        let m = Material::from_id(material_id);
+        let (x0, x1) = (x0.min(x1), x0.max(x1).min(self.width.saturating_sub(1)));
+        let (y0, y1) = (y0.min(y1), y0.max(y1).min(self.height.saturating_sub(1)));
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                self.paint(x as i32, y as i32, m);
+            }
+        }
+        self.write_pixels();
+    }
+
+    /// Set a steady wind over the whole grid, in cells per tick; positive blows right and down
+    pub fn set_wind(&mut self, vx: f32, vy: f32) {
+        self.set_wind_rect(0, 0, self.width, self.height, vx, vy);
+    }
+
+    /// Set a steady wind over the wind squares a rectangle touches, corners inclusive
+    pub fn set_wind_rect(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, vx: f32, vy: f32) {
+        let (x0, x1) = (x0.min(x1), x0.max(x1).min(self.width.saturating_sub(1)));
+        let (y0, y1) = (y0.min(y1), y0.max(y1).min(self.height.saturating_sub(1)));
+        for y in (y0 - y0 % wind::WIND_CELL..=y1).step_by(wind::WIND_CELL as usize) {
+            for x in (x0 - x0 % wind::WIND_CELL..=x1).step_by(wind::WIND_CELL as usize) {
+                let i = self.wind.square(x as i32, y as i32);
+                self.wind.steady[i] = [vx, vy];
+                // whatever lies there may now be blown away
+                self.chunks.wake(x as i32, y as i32);
+            }
+        }
+    }
+
+    /// Replace the rng state, so a loaded snapshot can be replayed with a different seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = seed_rng(seed);
+    }
+
+    /// Number of threads `step` spreads work over, 1 unless changed with `set_threads`
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Number of `step` calls so far
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Number of chunks the next tick will update, the rest of the grid is asleep
+    pub fn awake_chunks(&self) -> usize {
+        self.chunks.pending_count()
+    }
+
     /// Clear the simulation
     pub fn clear(&mut self) {
         for c in &mut self.cells {
-            *c = Cell::empty_with_clock(self.generation.wrapping_add(1));
+            *c = Cell::empty_with_clock(self.generation);
         }
+        self.temp.fill(AMBIENT_TEMPERATURE);
+        self.pressure.fill(0.0);
+        self.oxygen.fill(AMBIENT_OXYGEN);
+        self.wind.gusts.fill([0.0; 2]);
+        self.chunks.wake_all();
         self.write_pixels();
     }
+
+    /// Copy the simulation into a grid of another size, anchored to the bottom left so the
+    /// ground stays under whatever rests on it. Cells that no longer fit are dropped and new
+    /// ones start as empty air; the rng, generation and frame carry over.
+    pub fn resized(&self, width: u32, height: u32) -> Simulation {
+        let mut sim = Simulation::with_seed(width, height, 0);
+        let (w, h) = (self.width.min(width) as usize, self.height.min(height) as usize);
+        for y in 0..h {
+            let from = (self.height as usize - h + y) * self.width as usize;
+            let to = (height as usize - h + y) * width as usize;
+            sim.cells[to..to + w].copy_from_slice(&self.cells[from..from + w]);
+            sim.temp[to..to + w].copy_from_slice(&self.temp[from..from + w]);
+            sim.pressure[to..to + w].copy_from_slice(&self.pressure[from..from + w]);
+            sim.oxygen[to..to + w].copy_from_slice(&self.oxygen[from..from + w]);
+        }
+        sim.generation = self.generation;
+        sim.frame = self.frame;
+        sim.rng = self.rng;
+        sim.chunks.wake_all();
+        sim.write_pixels();
+        sim
+    }
+}
+
+/// Raw buffer views for JS, which reads them straight out of wasm memory
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Simulation {
+    pub fn pixels_ptr(&self) -> *const u8 {
+        self.pixels.as_ptr()
+    }
+
+    pub fn pixels_len(&self) -> usize {
+        self.pixels.len()
+    }
+
+    pub fn glow_pixels_ptr(&self) -> *const u8 {
+        self.glow_pixels.as_ptr()
+    }
+
+    pub fn glow_pixels_len(&self) -> usize {
+        self.glow_pixels.len()
+    }
+
+    pub fn temperature_ptr(&self) -> *const f32 {
+        self.temp.as_ptr()
+    }
+
+    pub fn temperature_len(&self) -> usize {
+        self.temp.len()
+    }
 }
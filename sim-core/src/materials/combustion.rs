@@ -0,0 +1,123 @@
+//! Burning.
+//!
+//! Every material with a `flammability` is a fuel and burns by the same rule. A cell of it
+//! with air, gas or a flame beside it catches, `flammability` of the time each tick, once
+//! the heat grid has brought it to its `ignition_temperature`, so flames, embers and lava
+//! light it by warming it up. A burning cell keeps its material. It holds the temperature
+//! of a flame, which warms the fuel around it in turn, and gives off short-lived fire into
+//! the open cells beside it, so the flames last exactly as long as the fuel does. It burns
+//! the oxygen in the air beside it, and it goes out once that air is too stale to burn in.
+//!
+//! `rb` keeps how far the cell has burnt, which stays when it goes out and shows as
+//! charring, plus a bit for whether it is alight. After `burn_duration` ticks of burning it
+//! turns into one of its `combustion_products`. A liquid that doesn't burn puts it out.
+//!
+//! Fuel with a `blast_radius` doesn't burn down, it goes off the moment it catches.
+
+use super::{Group, Material, MaterialProps, props};
+use crate::oxygen::{FUEL_USE, SMOTHERED, holds_oxygen};
+use crate::{Cell, SimAPI, blast};
+
+const NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+/// A burning cell gives off a flame 1 in this many ticks
+const FLAME_RATE: u32 = 2;
+
+/// Bit of `rb` set while the cell burns, the rest count the ticks it has burnt
+const ALIGHT: u8 = 0x80;
+
+/// Longest burn_duration the rest of `rb` can count to
+pub(crate) const MAX_BURN_DURATION: u8 = !ALIGHT;
+
+/// Whether a cell of a fuel is burning
+#[inline]
+pub(super) fn alight(cell: Cell) -> bool {
+    cell.rb & ALIGHT != 0
+}
+
+/// Share of its fuel a cell has burnt, 0 to 1
+#[inline]
+pub(super) fn charring(cell: Cell, p: &MaterialProps) -> f32 {
+    ((cell.rb & !ALIGHT) as f32 / p.burn_duration.max(1) as f32).min(1.0)
+}
+
+/// Catch fire, or burn on if already alight. Returns true if the cell is used up.
+pub(super) fn burn(cell: Cell, api: &mut SimAPI, p: &MaterialProps) -> bool {
+    if !alight(cell) {
+        return catch(cell, api, p);
+    }
+    let burnt = cell.rb & !ALIGHT;
+
+    // doused, it takes the temperature of the liquid
+    for (dx, dy) in NEIGHBOURS {
+        let other = props(api.get(dx, dy).material);
+        if other.group == Group::Liquid && other.flammability == 0.0 {
+            let t = api.get_temp(dx, dy);
+            api.set_temp(0, 0, t);
+            api.set_rb(burnt);
+            return false;
+        }
+    }
+
+    // out of air it goes out, still too hot to touch, and catches again once air gets back
+    let Some((ax, ay)) = freshest_air(api) else {
+        api.set_rb(burnt);
+        return false;
+    };
+    api.use_oxygen(ax, ay, FUEL_USE);
+
+    if burnt >= p.burn_duration {
+        let ra = api.rand_u32() as u8;
+        let becomes = p.combustion_products.pick(api.rand_u32());
+        api.set(0, 0, Cell { material: becomes, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+        return true;
+    }
+
+    api.set_rb(ALIGHT | (burnt + 1));
+    if let Some(t) = props(Material::Fire).source_temperature {
+        api.set_temp(0, 0, t);
+    }
+    api.keep_awake();
+
+    if api.one_in(FLAME_RATE) {
+        let (dx, dy) = NEIGHBOURS[api.rand_u32() as usize % NEIGHBOURS.len()];
+        if api.get(dx, dy).material == Material::Empty {
+            let ra = api.rand_u32() as u8;
+            api.set(dx, dy, Cell { material: Material::Fire, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+        }
+    }
+    false
+}
+
+/// Set the cell alight if it's hot enough, has air to burn in and the chance comes up.
+/// Returns true if it exploded.
+fn catch(cell: Cell, api: &mut SimAPI, p: &MaterialProps) -> bool {
+    if api.temp_here() < p.ignition_temperature {
+        return false;
+    }
+    if freshest_air(api).is_none() {
+        return false;
+    }
+    if (api.rand_u32() as f32 / u32::MAX as f32) >= p.flammability {
+        api.keep_awake();
+        return false;
+    }
+
+    if p.blast_radius > 0 {
+        blast::explode(api, p.blast_radius as i32);
+        return true;
+    }
+    api.set_rb(cell.rb | ALIGHT);
+    false
+}
+
+/// The neighbour with the most oxygen in its air, if that is enough to burn in
+fn freshest_air(api: &SimAPI) -> Option<(i32, i32)> {
+    NEIGHBOURS
+        .into_iter()
+        .filter(|&(dx, dy)| holds_oxygen(api.get(dx, dy).material))
+        .map(|(dx, dy)| (api.get_oxygen(dx, dy), dx, dy))
+        .filter(|&(o, _, _)| o >= SMOTHERED)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, dx, dy)| (dx, dy))
+}
@@ -1,137 +1,90 @@
-use super::{Group, Material, props};
+use super::{Group, Material, hold_temp, props, sink};
+use crate::oxygen::{FLAME_USE, SMOTHERED};
 use crate::{Cell, SimAPI};
 
-const EMBER_PASSABLE: &[Material] = &[Material::Empty, Material::Smoke, Material::Steam];
+/// Burn the oxygen in the flame's cell. Returns false, having turned it to smoke, if there
+/// wasn't enough left to burn.
+fn breathe(api: &mut SimAPI) -> bool {
+    if api.get_oxygen(0, 0) < SMOTHERED {
+        let ra = api.rand_u32() as u8;
+        api.set(0, 0, Cell { material: Material::Smoke, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+        return false;
+    }
+    api.use_oxygen(0, 0, FLAME_USE);
+    true
+}
 
 pub(super) fn update_fire(cell: Cell, mut api: SimAPI) {
-    const HEAT_EMISSION: u8 = 230; // how hot fire makes its cell; drives diffusion to neighbours
-    const MAX_LIFESPAN: u8 = 180; // ticks until fire burns out
-    const ASH_CHANCE: u32 = 10; // 1-in-N chance to leave ash instead of vanishing
-    const WOOD_SPREAD_RATE: u32 = 4; // 1-in-N chance to ignite adjacent wood each tick
-    const SMOKE_SPAWN_RATE: u32 = 100; // 1-in-N chance to emit smoke upward each tick
-    const FALL_RATE: u32 = 6; // 1-in-N chance to drip down per tick
-
-    api.set_heat(0, 0, HEAT_EMISSION);
+    let p = props(cell.material);
 
-    // Water extinguishes fire
-    for (dx, dy) in [(0i32, -1i32), (-1, 0), (1, 0), (0, 1)] {
-        if api.get(dx, dy).material == Material::Water {
-            api.clear_here();
-            return;
-        }
+    // a flame holds its own temperature, diffusion passes it on to the neighbours
+    hold_temp(&mut api, p);
+    if !breathe(&mut api) {
+        return;
     }
 
     // rb counts lifetime; die into ash or empty
     let life = cell.rb.wrapping_add(1);
-    if life > MAX_LIFESPAN {
+    if life > p.max_life(cell.ra) {
         let ra = api.rand_u32() as u8;
         let below = api.get(0, 1).material;
         let on_solid = matches!(props(below).group, Group::Solid | Group::Powder);
-        let becomes = if on_solid && api.rand_u32() % ASH_CHANCE == 0 { Material::Ash } else { Material::Empty };
-        api.set(0, 0, Cell { material: becomes, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+        let becomes = if on_solid && api.one_in(p.ash_chance) { Material::Ash } else { Material::Empty };
+        api.set(0, 0, Cell { material: becomes, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
         return;
     }
 
     // write updated lifetime before velocity
     api.set_rb(life);
 
-    // Spread to adjacent wood
-    if api.rand_u32() % WOOD_SPREAD_RATE == 0 {
-        for (dx, dy) in [(0i32, -1i32), (-1, 0), (1, 0), (0, 1)] {
-            if api.get(dx, dy).material == Material::Wood {
-                let ra = api.rand_u32() as u8;
-                api.set(dx, dy, Cell { material: Material::Fire, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-            }
-        }
-    }
-
     // Spawn smoke above
-    if api.rand_u32() % SMOKE_SPAWN_RATE == 0 && api.get(0, -1).material == Material::Empty {
+    if api.one_in(p.smoke_rate) && api.get(0, -1).material == Material::Empty {
         let ra = api.rand_u32() as u8;
-        api.set(0, -1, Cell { material: Material::Smoke, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+        api.set(0, -1, Cell { material: Material::Smoke, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
     }
 
     api.apply_gravity();
     api.resolve_velocity();
     let cell = api.get(0, 0);
 
-    if api.rand_u32() % FALL_RATE == 0 {
-        if api.try_move_into(0, 1, cell, EMBER_PASSABLE) {
-            return;
-        }
-        let left_first = api.rand_u32() & 1 == 0;
-        if left_first {
-            if api.try_move_into(-1, 1, cell, EMBER_PASSABLE) {
-                return;
-            }
-            if api.try_move_into(1, 1, cell, EMBER_PASSABLE) {
-                return;
-            }
-        } else {
-            if api.try_move_into(1, 1, cell, EMBER_PASSABLE) {
-                return;
-            }
-            if api.try_move_into(-1, 1, cell, EMBER_PASSABLE) {
-                return;
-            }
-        }
+    // drip down through the air
+    if api.one_in(p.fall_rate) {
+        sink(&mut api, cell, 1);
     }
 }
 
 pub(super) fn update_ember(cell: Cell, mut api: SimAPI) {
-    const HEAT_EMISSION: u8 = 180; // cooler than fire
-    const MIN_LIFESPAN: u8 = 20; // embers burn out fast
-    const LIFESPAN_VARIANCE: u8 = 5; // ra/VARIANCE added on top
-    const REIGNITE_CHANCE: u32 = 4; // 1-in-N chance to become fire instead of smoke on burnout
-    const WOOD_IGNITE_RATE: u32 = 4; // 1-in-N chance to set adjacent wood alight each tick
+    let p = props(cell.material);
 
-    api.set_heat(0, 0, HEAT_EMISSION);
+    hold_temp(&mut api, p);
+    if !breathe(&mut api) {
+        return;
+    }
 
     let life = cell.rb.wrapping_add(1);
-    let max_life = MIN_LIFESPAN.saturating_add(cell.ra / LIFESPAN_VARIANCE);
-    if life > max_life {
+    if life > p.max_life(cell.ra) {
         let ra = api.rand_u32() as u8;
-        let becomes = if api.rand_u32() % REIGNITE_CHANCE == 0 { Material::Fire } else { Material::Smoke };
-        api.set(0, 0, Cell { material: becomes, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+        // burns out into smoke, or sometimes flares back into fire
+        let becomes = if api.one_in(p.reignite_chance) { Material::Fire } else { Material::Smoke };
+        api.set(0, 0, Cell { material: becomes, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
         return;
     }
 
     api.set_rb(life);
 
-    // Ignite adjacent wood
-    if api.rand_u32() % WOOD_IGNITE_RATE == 0 {
-        for (dx, dy) in [(0i32, -1i32), (-1, 0), (1, 0), (0, 1)] {
-            if api.get(dx, dy).material == Material::Wood {
-                let ra = api.rand_u32() as u8;
-                api.set(dx, dy, Cell { material: Material::Fire, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-            }
-        }
-    }
-
     api.apply_gravity();
     api.resolve_velocity();
     let cell = api.get(0, 0);
 
-    if cell.vy == 0 {
-        if api.try_move_into(0, -1, cell, EMBER_PASSABLE) {
-            return;
-        }
+    if cell.vy == 0 && api.try_displace(0, -1, cell) {
+        return;
     }
 
     // horizontal drifting
     let left_first = api.rand_u32() & 1 == 0;
-    if left_first {
-        if api.try_move_into(-1, 0, cell, EMBER_PASSABLE) {
-            return;
-        }
-        if api.try_move_into(1, 0, cell, EMBER_PASSABLE) {
-            return;
-        }
-    } else {
-        if api.try_move_into(1, 0, cell, EMBER_PASSABLE) {
-            return;
-        }
-        if api.try_move_into(-1, 0, cell, EMBER_PASSABLE) {
+    let dirs: [i32; 2] = if left_first { [-1, 1] } else { [1, -1] };
+    for dx in dirs {
+        if api.try_displace(dx, 0, cell) {
             return;
         }
     }
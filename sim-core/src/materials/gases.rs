@@ -1,16 +1,17 @@
-use super::Material;
+use super::{Material, props};
 use crate::{Cell, SimAPI};
 
-const SMOKE_PASSABLE: &[Material] = &[Material::Empty, Material::Sand, Material::Water, Material::Ash, Material::Steam, Material::Lava, Material::Acid];
-const STEAM_PASSABLE: &[Material] = &[Material::Empty, Material::Smoke, Material::Water, Material::Ash, Material::Lava, Material::Acid];
+const NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+/// Degrees below its condense point at which a gas condenses at its full rate
+const CONDENSE_SPAN: f32 = 40.0;
 
 pub(super) fn update_smoke(cell: Cell, mut api: SimAPI) {
-    const MIN_LIFESPAN: u8 = 80; // ticks before a smoke particle can disappear
-    const LIFESPAN_VARIANCE: u8 = 4; // ra divided by this is added on top, so each particle lasts a bit differently
+    let p = props(cell.material);
 
+    // ra adds on to the lifespan, so each particle lasts a bit differently
     let life = cell.rb.wrapping_add(1);
-    let max_life = MIN_LIFESPAN.saturating_add(cell.ra / LIFESPAN_VARIANCE);
-    if life > max_life {
+    if life > p.max_life(cell.ra) {
         api.clear_here();
         return;
     }
@@ -22,28 +23,17 @@ pub(super) fn update_smoke(cell: Cell, mut api: SimAPI) {
     api.resolve_velocity();
     let cell = api.get(0, 0);
 
-    // if blocked above, try displacement through passable materials
-    if cell.vy == 0 {
-        if api.try_move_into(0, -1, cell, SMOKE_PASSABLE) {
-            return;
-        }
+    // if blocked above, rise through anything denser
+    if cell.vy == 0 && api.try_displace(0, -1, cell) {
+        return;
     }
 
     // horizontal drifting
-    if api.rand_u32() % 10 == 0 {
+    if api.one_in(p.drift_rate) {
         let left_first = api.rand_u32() & 1 == 0;
-        if left_first {
-            if api.try_move_into(-1, 0, cell, SMOKE_PASSABLE) {
-                return;
-            }
-            if api.try_move_into(1, 0, cell, SMOKE_PASSABLE) {
-                return;
-            }
-        } else {
-            if api.try_move_into(1, 0, cell, SMOKE_PASSABLE) {
-                return;
-            }
-            if api.try_move_into(-1, 0, cell, SMOKE_PASSABLE) {
+        let dirs: [i32; 2] = if left_first { [-1, 1] } else { [1, -1] };
+        for dx in dirs {
+            if api.try_displace(dx, 0, cell) {
                 return;
             }
         }
@@ -51,46 +41,40 @@ pub(super) fn update_smoke(cell: Cell, mut api: SimAPI) {
 }
 
 pub(super) fn update_steam(cell: Cell, mut api: SimAPI) {
-    const LIFE_INCREMENT_RATE: u32 = 20; // only ages 1-in-N ticks, so steam lingers much longer than smoke
-    const MIN_LIFESPAN: u8 = 80; // minimum lifespan before condensing back to water
-    const LIFESPAN_VARIANCE: u8 = 2; // ra/VARIANCE added on top - larger divisor = less variance than smoke
+    let p = props(cell.material);
 
-    let life = if api.rand_u32() % LIFE_INCREMENT_RATE == 0 { cell.rb.wrapping_add(1) } else { cell.rb };
-    let max_life = MIN_LIFESPAN.saturating_add(cell.ra / LIFESPAN_VARIANCE);
-    if life > max_life {
-        let ra = api.rand_u32() as u8;
-        api.set(0, 0, Cell { material: Material::Water, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-        return;
+    // condenses into droplets where it or what it touches is cold, the colder the sooner,
+    // giving back the heat and pressure the water took to boil
+    let t = api.temp_here();
+    let coldest = NEIGHBOURS.iter().map(|&(dx, dy)| api.get_temp(dx, dy)).fold(t, f32::min);
+    if coldest < p.condense_point {
+        let chill = ((p.condense_point - coldest) / CONDENSE_SPAN).min(1.0).powi(2);
+        if api.one_in(p.condense_rate) && (api.rand_u32() as f32 / u32::MAX as f32) < chill {
+            let water = props(Material::Water);
+            let ra = api.rand_u32() as u8;
+            api.set(0, 0, Cell { material: Material::Water, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+            api.set_temp(0, 0, t + water.latent_heat);
+            api.add_pressure(0, 0, -water.boil_pressure);
+            return;
+        }
+        // keep rolling, even stuck still under a cold ceiling
+        api.keep_awake();
     }
 
-    // write updated lifteime before velocity moves the cell
-    api.set_rb(life);
-
     api.apply_gravity();
     api.resolve_velocity();
     let cell = api.get(0, 0);
 
-    // if blocked above, try displacement through passable materials
-    if cell.vy == 0 {
-        if api.try_move_into(0, -1, cell, STEAM_PASSABLE) {
-            return;
-        }
+    // if blocked above, rise through anything denser
+    if cell.vy == 0 && api.try_displace(0, -1, cell) {
+        return;
     }
 
-    if api.rand_u32() % 10 == 0 {
+    if api.one_in(p.drift_rate) {
         let left_first = api.rand_u32() & 1 == 0;
-        if left_first {
-            if api.try_move_into(-1, 0, cell, STEAM_PASSABLE) {
-                return;
-            }
-            if api.try_move_into(1, 0, cell, STEAM_PASSABLE) {
-                return;
-            }
-        } else {
-            if api.try_move_into(1, 0, cell, STEAM_PASSABLE) {
-                return;
-            }
-            if api.try_move_into(-1, 0, cell, STEAM_PASSABLE) {
+        let dirs: [i32; 2] = if left_first { [-1, 1] } else { [1, -1] };
+        for dx in dirs {
+            if api.try_displace(dx, 0, cell) {
                 return;
             }
         }
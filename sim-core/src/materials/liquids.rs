@@ -1,249 +1,107 @@
-use super::Material;
+use super::{Material, MaterialProps, props, sink};
 use crate::{Cell, SimAPI};
 
-const LAVA_DISSOLVES: &[(Material, u32)] = &[(Material::Stone, 20), (Material::Sand, 10), (Material::Ash, 1)];
-const WATER_DISSOLVES: &[(Material, u32)] = &[(Material::Ash, 1)];
-const ACID_DISSOLVES: &[(Material, u32)] = &[
-    (Material::Ash, 1),
-    (Material::Gunpowder, 3),
-    (Material::Sand, 5),
-    (Material::Ice, 8),
-    (Material::Wood, 10),
-    (Material::Oil, 12),
-    (Material::Stone, 20),
-    (Material::Obsidian, 40),
-    (Material::Wall, 50),
-];
+/// Viscosity molten material gains as it cools from its initial temperature to setting
+const SETTING_VISCOSITY: f32 = 20.0;
 
-pub(super) fn update_water(_cell: Cell, mut api: SimAPI) {
-    const BOILING_POINT: u8 = 45;
-    const BOILING_RATE: u32 = 20; // higher value means slower boil
-    const DISPERSION: i32 = 7;
+pub(super) fn update_liquid(cell: Cell, mut api: SimAPI) {
+    let p = props(cell.material);
 
-    // Boil to steam when heat is high enough
-    if api.heat_here() > BOILING_POINT && api.rand_u32() % BOILING_RATE == 0 {
-        let ra = api.rand_u32() as u8;
-        api.set(0, 0, Cell { material: Material::Steam, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+    // Boil to steam when hot enough
+    let t = api.temp_here();
+    if t > p.boil_point && api.one_in(p.boil_rate) {
+        vaporise(&mut api, p);
         return;
     }
 
-    api.apply_gravity();
-    api.resolve_velocity();
-    let cell = api.get(0, 0);
-
-    // post-velocity dissolving, if resting on a dissolvable material, fall thru it
-    if cell.vy == 0 {
-        let below = api.get(0, 1);
-        for &(mat, rate) in WATER_DISSOLVES {
-            if below.material == mat && (rate == 1 || api.rand_u32() % rate == 0) {
-                api.set(0, 1, cell);
-                api.clear_here();
-                return;
-            }
-        }
-    }
-
-    // Water is denser than oil - sink through it
-    let left_first = ((api.generation() as u32) ^ api.rand_u32()) & 1 == 0;
-    if api.try_move_into(0, 1, cell, &[Material::Oil]) {
-        return;
-    }
-    if left_first {
-        if api.try_move_into(-1, 1, cell, &[Material::Oil]) {
-            return;
-        }
-        if api.try_move_into(1, 1, cell, &[Material::Oil]) {
-            return;
-        }
-    } else {
-        if api.try_move_into(1, 1, cell, &[Material::Oil]) {
-            return;
-        }
-        if api.try_move_into(-1, 1, cell, &[Material::Oil]) {
+    // lying still with air above it, it slowly evaporates, even at ordinary temperatures,
+    // the chance climbing towards boiling the way vapour pressure does
+    if p.evaporation_rate > 0 && cell.vy == 0 && api.get(0, -1).material == Material::Empty {
+        api.keep_awake();
+        let warmth = (t / p.boil_point).clamp(0.0, 1.0).powi(2);
+        if api.one_in(p.evaporation_rate) && (api.rand_u32() as f32 / u32::MAX as f32) < warmth {
+            vaporise(&mut api, p);
             return;
         }
     }
 
-    // Scan sideways up to DISPERSION cells, move to farthest clear spot
-    let dirs: [i32; 2] = if left_first { [-1, 1] } else { [1, -1] };
-    for dir in dirs {
-        let mut max = 0;
-        for d in 1..=DISPERSION {
-            let m = api.get(dir * d, 0).material;
-            if m == Material::Ash {
-                max = d;
-                break;
-            }
-            if m != Material::Empty {
-                break;
-            }
-            max = d;
-        }
-        if max > 0 && api.try_move_dissolving(dir * max, 0, cell, WATER_DISSOLVES) {
-            return;
-        }
-    }
+    flow(&mut api, p.viscosity, p.dispersion);
 }
 
-pub(super) fn update_lava(_cell: Cell, mut api: SimAPI) {
-    const HEAT_EMISSION: u8 = 254;
-    const WOOD_IGNITE_RATE: u32 = 8;
-    const EMBER_SPAWN_RATE: u32 = 500; // higher = less likely
-    const SMOKE_SPAWN_RATE: u32 = 200;
-    const VISCOSITY: u32 = 3; // higher = more viscous
+/// Turn into steam, which adds to the air pressure. The latent heat comes out of the liquid
+/// underneath, the way evaporation cools a pool, or out of the steam if there is none.
+fn vaporise(api: &mut SimAPI, p: &MaterialProps) {
+    let (t, material) = (api.temp_here(), api.get(0, 0).material);
+    let ra = api.rand_u32() as u8;
+    api.set(0, 0, Cell { material: Material::Steam, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+    if api.get(0, 1).material == material {
+        let under = api.get_temp(0, 1);
+        api.set_temp(0, 1, under - p.latent_heat);
+    } else {
+        api.set_temp(0, 0, t - p.latent_heat);
+    }
+    api.add_pressure(0, 0, p.boil_pressure);
+}
 
-    api.set_heat(0, 0, HEAT_EMISSION);
+pub(super) fn update_lava(cell: Cell, mut api: SimAPI) {
+    let p = props(cell.material);
 
-    // Touching water: become obsidian, burst steam
-    for (dx, dy) in [(0i32, -1i32), (-1, 0), (1, 0), (0, 1)] {
-        if api.get(dx, dy).material == Material::Water {
+    // it sets into the solid it is molten from, the reverse of that solid melting
+    let t = api.temp_here();
+    let setting = if p.freezes_into == Material::Empty { f32::NEG_INFINITY } else { props(p.freezes_into).melt_point };
+    if t < setting {
+        if api.one_in(p.freeze_rate) {
             let ra = api.rand_u32() as u8;
-            api.set(dx, dy, Cell { material: Material::Steam, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-            for (sdx, sdy) in [(-1i32, -2i32), (0, -2), (1, -2), (-1, -1), (1, -1), (-2, 0), (2, 0)] {
-                if api.get(sdx, sdy).material == Material::Empty {
-                    let ra = api.rand_u32() as u8;
-                    api.set(sdx, sdy, Cell { material: Material::Steam, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-                }
-            }
-            let ra2 = api.rand_u32() as u8;
-            api.set(0, 0, Cell { material: Material::Obsidian, ra: ra2, rb: 0, clock: 0, vx: 0, vy: 0 });
+            api.set(0, 0, Cell { material: p.freezes_into, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
             return;
         }
-    }
-
-    // Ignite adjacent wood
-    if api.rand_u32() % WOOD_IGNITE_RATE == 0 {
-        for (dx, dy) in [(0i32, -1i32), (-1, 0), (1, 0), (0, 1)] {
-            if api.get(dx, dy).material == Material::Wood {
+    } else {
+        // Occasionally shoot an ember upward
+        if api.one_in(p.ember_rate) {
+            let offsets = [(-1i32, -1i32), (0, -1), (1, -1), (-2, -1), (2, -1)];
+            let idx = (api.rand_u32() as usize) % offsets.len();
+            let (dx, dy) = offsets[idx];
+            if api.get(dx, dy).material == Material::Empty {
                 let ra = api.rand_u32() as u8;
-                api.set(dx, dy, Cell { material: Material::Fire, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+                api.set(dx, dy, Cell { material: Material::Ember, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
             }
         }
-    }
 
-    // Occasionally shoot an ember upward
-    if api.rand_u32() % EMBER_SPAWN_RATE == 0 {
-        let offsets = [(-1i32, -1i32), (0, -1), (1, -1), (-2, -1), (2, -1)];
-        let idx = (api.rand_u32() as usize) % offsets.len();
-        let (dx, dy) = offsets[idx];
-        if api.get(dx, dy).material == Material::Empty {
+        // Surface lava emits smoke
+        if api.one_in(p.smoke_rate) && api.get(0, -1).material == Material::Empty {
             let ra = api.rand_u32() as u8;
-            api.set(dx, dy, Cell { material: Material::Ember, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+            api.set(0, -1, Cell { material: Material::Smoke, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
         }
     }
 
-    // Surface lava emits smoke
-    if api.rand_u32() % SMOKE_SPAWN_RATE == 0 && api.get(0, -1).material == Material::Empty {
-        let ra = api.rand_u32() as u8;
-        api.set(0, -1, Cell { material: Material::Smoke, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-    }
+    // runny while hot, thickening as it cools towards setting
+    let hot = p.initial_temperature.unwrap_or(setting).max(setting);
+    let cooled = if hot > setting { ((hot - t) / (hot - setting)).clamp(0.0, 1.0) } else { 0.0 };
+    flow(&mut api, p.viscosity + (cooled * SETTING_VISCOSITY) as u32, p.dispersion);
+}
 
-    // gravity accumulates every tick
+/// Movement every liquid shares: fall, sink through anything lighter, else spread along
+/// the row. Gravity builds up every tick but the cell only moves 1 in `viscosity` ticks,
+/// and looks up to `dispersion` cells to each side for the farthest clear spot. That spread
+/// comes on top of the fall, so the two share the reach the material table allows.
+fn flow(api: &mut SimAPI, viscosity: u32, dispersion: u8) {
     api.apply_gravity();
-
-    // only resolve movement 1 in VISCOSITY ticks
-    if api.rand_u32() % VISCOSITY != 0 {
+    if !api.one_in(viscosity) {
         return;
     }
 
     api.resolve_velocity();
     let cell = api.get(0, 0);
 
-    //post velocity dissolving
-    if cell.vy == 0 {
-        let below = api.get(0, 1);
-        for &(mat, rate) in LAVA_DISSOLVES {
-            if below.material == mat && (rate == 1 || api.rand_u32() % rate == 0) {
-                api.set(0, 1, cell);
-                api.clear_here();
-                return;
-            }
-        }
-    }
-    // horizontal spreading
-    let left_first = ((api.generation() as u32) ^ api.rand_u32()) & 1 == 0;
-    if left_first {
-        if api.try_move_dissolving(-1, 0, cell, LAVA_DISSOLVES) {
-            return;
-        }
-        if api.try_move_dissolving(1, 0, cell, LAVA_DISSOLVES) {
-            return;
-        }
-    } else {
-        if api.try_move_dissolving(1, 0, cell, LAVA_DISSOLVES) {
-            return;
-        }
-        if api.try_move_dissolving(-1, 0, cell, LAVA_DISSOLVES) {
-            return;
-        }
-    }
-}
-
-pub(super) fn update_oil(_cell: Cell, mut api: SimAPI) {
-    const DISPERSION: i32 = 5;
-    const IGNITE_SOURCES: &[Material] = &[Material::Fire, Material::Lava, Material::Ember];
-    const IGNITE_CHANCE: u32 = 12; // 1-in-N chance to catch fire each tick when adjacent
-    // Ignite from adjacent fire/lava/ember
-    for (dx, dy) in [(0i32, -1i32), (-1, 0), (1, 0), (0, 1)] {
-        if IGNITE_SOURCES.contains(&api.get(dx, dy).material) && api.rand_u32() % IGNITE_CHANCE == 0 {
-            let ra = api.rand_u32() as u8;
-            api.set(0, 0, Cell { material: Material::Fire, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-            return;
-        }
-    }
-
-    api.apply_gravity();
-    api.resolve_velocity();
-    let cell = api.get(0, 0);
-
-    // Spread sideways
-    let left_first = ((api.generation() as u32) ^ api.rand_u32()) & 1 == 0;
-    let dirs: [i32; 2] = if left_first { [-1, 1] } else { [1, -1] };
-    for dir in dirs {
-        let mut max = 0;
-        for d in 1..=DISPERSION {
-            if api.get(dir * d, 0).material != Material::Empty {
-                break;
-            }
-            max = d;
-        }
-        if max > 0 && api.try_move(dir * max, 0, cell) {
-            return;
-        }
-    }
-}
-
-pub(super) fn update_acid(_cell: Cell, mut api: SimAPI) {
-    const VISCOSITY: u32 = 3;
-    const DISPERSION: i32 = 2;
-
-    // dissolve one adjacent material per tick (runs every tick regardless of viscosity)
-    for (dx, dy) in [(0i32, 1i32), (-1, 0), (1, 0), (0, -1)] {
-        let target = api.get(dx, dy).material;
-        if let Some(&(_, rate)) = ACID_DISSOLVES.iter().find(|&&(m, _)| m == target) {
-            if rate == 1 || api.rand_u32() % rate == 0 {
-                let ra = api.rand_u32() as u8;
-                api.set(dx, dy, Cell { material: Material::Empty, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-                break;
-            }
-        }
-    }
-
-    api.apply_gravity();
-
-    if api.rand_u32() % VISCOSITY != 0 {
+    if sink(api, cell, 1) {
         return;
     }
 
-    api.resolve_velocity();
-    let cell = api.get(0, 0);
-
-    //horizontal spread
     let left_first = ((api.generation() as u32) ^ api.rand_u32()) & 1 == 0;
     let dirs: [i32; 2] = if left_first { [-1, 1] } else { [1, -1] };
     for dir in dirs {
         let mut max = 0;
-        for d in 1..=DISPERSION {
+        for d in 1..=dispersion as i32 {
             if api.get(dir * d, 0).material != Material::Empty {
                 break;
             }
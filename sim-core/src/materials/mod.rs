@@ -1,34 +1,55 @@
+mod combustion;
 mod fire;
 mod gases;
 mod liquids;
 mod powders;
+mod reactions;
 mod solids;
+mod table;
 
 use crate::{Cell, SimAPI};
+use serde::Deserialize;
+use std::fmt;
 
-#[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Material {
-    Empty = 0,
-    Wall = 1,
-    Sand = 2,
-    Water = 3,
-    Stone = 4,
-    Wood = 5,
-    Fire = 6,
-    Smoke = 7,
-    Ash = 8,
-    Lava = 9,
-    Steam = 10,
-    Obsidian = 11,
-    Acid = 12,
-    Ember = 13,
-    Oil = 14,
-    Ice = 15,
-    Gunpowder = 16,
+pub use table::{MaterialInfo, MaterialTable, MaterialsError, load_materials, materials};
+
+/// A material id as stored in cells. Which ids exist, and what they do, comes from the
+/// material table, see `materials.toml`.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Material(u8);
+
+/// The built-in materials. The engine's own rules refer to these, so every material
+/// table defines them at these ids.
+#[allow(non_upper_case_globals)]
+impl Material {
+    pub const Empty: Material = Material(0);
+    pub const Wall: Material = Material(1);
+    pub const Sand: Material = Material(2);
+    pub const Water: Material = Material(3);
+    pub const Stone: Material = Material(4);
+    pub const Wood: Material = Material(5);
+    pub const Fire: Material = Material(6);
+    pub const Smoke: Material = Material(7);
+    pub const Ash: Material = Material(8);
+    pub const Lava: Material = Material(9);
+    pub const Steam: Material = Material(10);
+    pub const Obsidian: Material = Material(11);
+    pub const Acid: Material = Material(12);
+    pub const Ember: Material = Material(13);
+    pub const Oil: Material = Material(14);
+    pub const Ice: Material = Material(15);
+    pub const Gunpowder: Material = Material(16);
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl fmt::Debug for Material {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Group {
     Empty,
     Powder,
@@ -38,261 +59,339 @@ pub enum Group {
     Fire,
 }
 
-#[derive(Clone, Copy)]
+/// Update rule a material runs each tick. Materials sharing one differ only in their props.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Behaviour {
+    #[default]
+    Static,
+    /// any powder; gunpowder's old rule name still loads
+    #[serde(alias = "gunpowder")]
+    Powder,
+    /// any liquid; the names of the rules oil and acid used to have still load
+    #[serde(alias = "oil", alias = "acid")]
+    Water,
+    Lava,
+    Smoke,
+    Steam,
+    Fire,
+    Ember,
+    Stone,
+    Ice,
+    Wood,
+}
+
+impl Group {
+    /// Whether materials of the group block radiant heat unless the table says otherwise
+    pub fn opaque_by_default(self) -> bool {
+        matches!(self, Group::Powder | Group::Liquid | Group::Solid)
+    }
+}
+
+impl Behaviour {
+    /// Rule used when a material doesn't name one
+    pub fn default_for(group: Group) -> Self {
+        match group {
+            Group::Empty | Group::Solid => Behaviour::Static,
+            Group::Powder => Behaviour::Powder,
+            Group::Liquid => Behaviour::Water,
+            Group::Gas => Behaviour::Smoke,
+            Group::Fire => Behaviour::Fire,
+        }
+    }
+}
+
+/// Physical properties and behaviour parameters of a material, see `materials.toml`
+/// for what each one means
+#[derive(Clone, Copy, Debug, Deserialize)]
 pub struct MaterialProps {
     pub group: Group,
+    /// filled in from the group when the table doesn't name one
+    #[serde(skip)]
+    pub behaviour: Behaviour,
+    /// blocks radiant heat; filled in from the group when the table doesn't say
+    #[serde(skip)]
+    pub opaque: bool,
     pub colour: [u8; 4],
     pub colour_variation: [i16; 3],
+    #[serde(default)]
     pub glow: [u8; 4],
-    pub gravity: i8,
+    /// cells per tick added to the velocity every tick
+    #[serde(default)]
+    pub gravity: f32,
+    #[serde(default)]
     pub terminal_velocity: i8,
-    pub slide_acceleration: i8,
+    #[serde(default)]
+    pub slide_acceleration: f32,
+    #[serde(default = "one")]
     pub air_drag: f32,
+    #[serde(default = "one")]
     pub base_friction: f32,
+    #[serde(default = "one")]
     pub surface_slipperiness: f32,
+    /// 0 to 1, share of the wind's velocity it picks up per tick
+    #[serde(default)]
+    pub wind_response: f32,
+    /// kg per cubic metre, what it sinks through and how hard it is to fling
+    #[serde(default = "water_density")]
+    pub density: f32,
+    /// 0 to 1, share of its speed it bounces back with after hitting something
+    #[serde(default)]
+    pub restitution: f32,
+    /// solid whose connected lumps float on denser liquids and sink in lighter ones
+    #[serde(default)]
+    pub buoyant: bool,
+
+    /// relative, how much heat it takes to change the temperature by a degree
+    #[serde(default = "one")]
+    pub heat_capacity: f32,
+    /// 0 to 1, how readily heat passes into and out of it
+    #[serde(default = "air_conductivity")]
+    pub thermal_conductivity: f32,
+    /// burning and molten materials hold their own temperature while they last
+    #[serde(default)]
+    pub source_temperature: Option<f32>,
+    /// molten materials that cool instead start out at this temperature when placed
+    #[serde(default)]
+    pub initial_temperature: Option<f32>,
+    /// 0 to 1, how strongly it radiates heat to what it can see when glowing hot
+    #[serde(default)]
+    pub emissivity: f32,
+    #[serde(default = "max_u8")]
+    pub lifespan: u8,
+    #[serde(default)]
+    pub lifespan_variance: u8,
+    #[serde(default = "never")]
+    pub boil_point: f32,
+    #[serde(default = "one")]
+    pub boil_rate: u32,
+    /// pressure the steam a cell turns into adds to the air, taken back when it condenses
+    #[serde(default)]
+    pub boil_pressure: f32,
+    /// exposed to air, a liquid evaporates 1 in this many ticks at its boil point, less
+    /// often the cooler it is; 0 for never
+    #[serde(default)]
+    pub evaporation_rate: u32,
+    /// degrees a liquid cools by turning to steam, and warms by condensing back
+    #[serde(default)]
+    pub latent_heat: f32,
+    /// a gas condenses where it, or anything it touches, is colder than this
+    #[serde(default = "cold")]
+    pub condense_point: f32,
+    #[serde(default = "one")]
+    pub condense_rate: u32,
+    #[serde(default = "never")]
+    pub melt_point: f32,
+    #[serde(default = "one")]
+    pub melt_rate: u32,
+    /// the solid a molten material sets into below that solid's melt point; filled in from
+    /// the table's name for it, empty for never
+    #[serde(skip, default = "nothing")]
+    pub freezes_into: Material,
+    #[serde(default = "one")]
+    pub freeze_rate: u32,
+    #[serde(default)]
+    pub smoke_rate: u32,
+    #[serde(default)]
+    pub ember_rate: u32,
+    #[serde(default)]
+    pub ash_chance: u32,
+    #[serde(default)]
+    pub reignite_chance: u32,
+    #[serde(default)]
+    pub fall_rate: u32,
+    #[serde(default)]
+    pub drift_rate: u32,
+    #[serde(default)]
+    pub dispersion: u8,
+    #[serde(default = "one")]
+    pub viscosity: u32,
+    #[serde(default)]
+    pub blast_radius: u8,
+    /// 0 to 1, chance a tick it catches fire once hot enough, 0 for never
+    #[serde(default)]
+    pub flammability: f32,
+    #[serde(default = "never")]
+    pub ignition_temperature: f32,
+    /// ticks a burning cell lasts
+    #[serde(default)]
+    pub burn_duration: u8,
+    /// what a burnt-out cell turns into; filled in from the table's names for them
+    #[serde(skip)]
+    pub combustion_products: CombustionProducts,
+    /// pressure difference it holds back before breaking, 0 for never
+    #[serde(default)]
+    pub strength: f32,
+    /// what it breaks into; filled in from the table's name for it
+    #[serde(skip, default = "nothing")]
+    pub rubble: Material,
+}
+
+/// Most materials a fuel can list as its combustion products
+pub(crate) const MAX_COMBUSTION_PRODUCTS: usize = 4;
+
+/// Materials a burnt-out cell may turn into, each as likely as the others
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CombustionProducts {
+    materials: [Material; MAX_COMBUSTION_PRODUCTS],
+    len: u8,
+}
+
+impl Default for CombustionProducts {
+    fn default() -> Self {
+        CombustionProducts { materials: [Material::Empty; MAX_COMBUSTION_PRODUCTS], len: 1 }
+    }
 }
 
-const PROPS: [MaterialProps; 17] = [
-    // empty
-    MaterialProps {
-        group: Group::Empty,
-        colour: [0, 0, 0, 255],
-        colour_variation: [7, 7, 7],
-        glow: [0, 0, 0, 0],
-        gravity: 0,
-        terminal_velocity: 0,
-        slide_acceleration: 0,
-        air_drag: 1.0,
-        base_friction: 1.0,
-        surface_slipperiness: 1.0,
-    },
-    // wall
-    MaterialProps {
-        group: Group::Solid,
-        colour: [100, 95, 90, 255],
-        colour_variation: [9, 9, 9],
-        glow: [0, 0, 0, 0],
-        gravity: 0,
-        terminal_velocity: 0,
-        slide_acceleration: 0,
-        air_drag: 1.0,
-        base_friction: 1.0,
-        surface_slipperiness: 0.85,
-    },
-    // sand
-    MaterialProps {
-        group: Group::Powder,
-        colour: [210, 185, 110, 255],
-        colour_variation: [5, 7, 12],
-        glow: [0, 0, 0, 0],
-        gravity: 1,
-        terminal_velocity: 10,
-        slide_acceleration: 1,
-        air_drag: 0.95,
-        base_friction: 0.85,
-        surface_slipperiness: 0.82,
-    },
-    // water
-    MaterialProps {
-        group: Group::Liquid,
-        colour: [40, 110, 210, 160],
-        colour_variation: [12, 7, 4],
-        glow: [0, 0, 0, 0],
-        gravity: 1,
-        terminal_velocity: 8,
-        slide_acceleration: 3,
-        air_drag: 0.95,
-        base_friction: 0.95,
-        surface_slipperiness: 0.96,
-    },
-    // stone
-    MaterialProps {
-        group: Group::Solid,
-        colour: [110, 110, 115, 255],
-        colour_variation: [7, 7, 7],
-        glow: [0, 0, 0, 0],
-        gravity: 0,
-        terminal_velocity: 0,
-        slide_acceleration: 0,
-        air_drag: 1.0,
-        base_friction: 1.0,
-        surface_slipperiness: 0.85,
-    },
-    // wood
-    MaterialProps {
-        group: Group::Solid,
-        colour: [120, 75, 30, 255],
-        colour_variation: [8, 6, 4],
-        glow: [0, 0, 0, 0],
-        gravity: 0,
-        terminal_velocity: 0,
-        slide_acceleration: 0,
-        air_drag: 1.0,
-        base_friction: 1.0,
-        surface_slipperiness: 0.82,
-    },
-    // fire
-    MaterialProps {
-        group: Group::Fire,
-        colour: [220, 60, 10, 255],
-        colour_variation: [2, 4, 20],
-        glow: [255, 120, 0, 200],
-        gravity: 0,
-        terminal_velocity: 4,
-        slide_acceleration: 0,
-        air_drag: 0.90,
-        base_friction: 1.0,
-        surface_slipperiness: 1.0,
-    },
-    // smoke
-    MaterialProps {
-        group: Group::Gas,
-        colour: [80, 80, 85, 180],
-        colour_variation: [6, 6, 6],
-        glow: [0, 0, 0, 0],
-        gravity: -1,
-        terminal_velocity: 1,
-        slide_acceleration: 0,
-        air_drag: 0.90,
-        base_friction: 1.0,
-        surface_slipperiness: 1.0,
-    },
-    // ash
-    MaterialProps {
-        group: Group::Powder,
-        colour: [160, 155, 145, 255],
-        colour_variation: [10, 10, 8],
-        glow: [0, 0, 0, 0],
-        gravity: 1,
-        terminal_velocity: 5,
-        slide_acceleration: 1,
-        air_drag: 0.95,
-        base_friction: 0.88,
-        surface_slipperiness: 0.80,
-    },
-    // lava
-    MaterialProps {
-        group: Group::Liquid,
-        colour: [207, 70, 10, 255],
-        colour_variation: [2, 6, 20],
-        glow: [255, 60, 0, 160],
-        gravity: 1,
-        terminal_velocity: 4,
-        slide_acceleration: 1,
-        air_drag: 0.95,
-        base_friction: 0.75,
-        surface_slipperiness: 0.70,
-    },
-    // steam
-    MaterialProps {
-        group: Group::Gas,
-        colour: [200, 220, 255, 160],
-        colour_variation: [10, 8, 6],
-        glow: [0, 0, 0, 0],
-        gravity: -1,
-        terminal_velocity: 1,
-        slide_acceleration: 0,
-        air_drag: 0.92,
-        base_friction: 1.0,
-        surface_slipperiness: 1.0,
-    },
-    // obsidian
-    MaterialProps {
-        group: Group::Solid,
-        colour: [25, 15, 40, 255],
-        colour_variation: [12, 12, 8],
-        glow: [0, 0, 0, 0],
-        gravity: 0,
-        terminal_velocity: 0,
-        slide_acceleration: 0,
-        air_drag: 1.0,
-        base_friction: 1.0,
-        surface_slipperiness: 0.80,
-    },
-    // acid
-    MaterialProps {
-        group: Group::Liquid,
-        colour: [3, 160, 45, 220],
-        colour_variation: [4, 12, 7],
-        glow: [0, 255, 60, 80],
-        gravity: 1,
-        terminal_velocity: 6,
-        slide_acceleration: 2,
-        air_drag: 0.95,
-        base_friction: 0.90,
-        surface_slipperiness: 0.92,
-    },
-    // ember
-    MaterialProps {
-        group: Group::Fire,
-        colour: [255, 160, 20, 255],
-        colour_variation: [2, 5, 20],
-        glow: [255, 140, 0, 220],
-        gravity: -1,
-        terminal_velocity: 1,
-        slide_acceleration: 0,
-        air_drag: 0.88,
-        base_friction: 1.0,
-        surface_slipperiness: 1.0,
-    },
-    // oil
-    MaterialProps {
-        group: Group::Liquid,
-        colour: [22, 20, 16, 255],
-        colour_variation: [8, 6, 4],
-        glow: [0, 0, 0, 0],
-        gravity: 1,
-        terminal_velocity: 6,
-        slide_acceleration: 2,
-        air_drag: 0.95,
-        base_friction: 0.96,
-        surface_slipperiness: 0.95,
-    },
-    // ice
-    MaterialProps {
-        group: Group::Solid,
-        colour: [160, 216, 240, 255],
-        colour_variation: [12, 10, 8],
-        glow: [0, 0, 0, 0],
-        gravity: 0,
-        terminal_velocity: 0,
-        slide_acceleration: 0,
-        air_drag: 1.0,
-        base_friction: 1.0,
-        surface_slipperiness: 0.98,
-    },
-    // gunpowder
-    MaterialProps {
-        group: Group::Powder,
-        colour: [60, 55, 50, 255],
-        colour_variation: [8, 8, 8],
-        glow: [0, 0, 0, 0],
-        gravity: 1,
-        terminal_velocity: 10,
-        slide_acceleration: 1,
-        air_drag: 0.95,
-        base_friction: 0.85,
-        surface_slipperiness: 0.82,
-    },
-];
+impl CombustionProducts {
+    /// Up to `MAX_COMBUSTION_PRODUCTS` materials, or None for too many or none at all
+    pub(crate) fn new(materials: &[Material]) -> Option<Self> {
+        if materials.is_empty() || materials.len() > MAX_COMBUSTION_PRODUCTS {
+            return None;
+        }
+        let mut products = CombustionProducts { len: materials.len() as u8, ..Default::default() };
+        products.materials[..materials.len()].copy_from_slice(materials);
+        Some(products)
+    }
+
+    pub fn as_slice(&self) -> &[Material] {
+        &self.materials[..self.len as usize]
+    }
+
+    /// The product for a random number
+    #[inline]
+    pub fn pick(&self, r: u32) -> Material {
+        self.materials[r as usize % self.len as usize]
+    }
+}
+
+fn one<T: From<u8>>() -> T {
+    T::from(1)
+}
+
+fn max_u8() -> u8 {
+    u8::MAX
+}
+
+fn water_density() -> f32 {
+    1000.0
+}
+
+fn air_conductivity() -> f32 {
+    0.2
+}
+
+fn nothing() -> Material {
+    Material::Empty
+}
+
+/// A temperature threshold that is never reached
+fn never() -> f32 {
+    f32::INFINITY
+}
+
+/// A temperature threshold that is never dropped below
+fn cold() -> f32 {
+    f32::NEG_INFINITY
+}
+
+impl MaterialProps {
+    /// Ticks a cell with this random shade lasts, for materials that burn out or fade
+    #[inline]
+    pub fn max_life(&self, ra: u8) -> u8 {
+        self.lifespan.saturating_add(ra.checked_div(self.lifespan_variance).unwrap_or(0))
+    }
+
+    /// Whether a cell of this material moving `dy` rows may swap places with one of
+    /// `other`. Empty always gives way. Up or down, the denser one ends up below, but
+    /// powders don't sink through each other and gases don't rise through them. Side by
+    /// side only gases mix. Flames and sparks pass through any gas, solids never move.
+    pub fn displaces(&self, other: &MaterialProps, dy: i32) -> bool {
+        match (self.group, other.group) {
+            (_, Group::Empty) => true,
+            (Group::Empty | Group::Solid, _) | (_, Group::Solid | Group::Fire) => false,
+            (Group::Fire, other) => other == Group::Gas,
+            (Group::Powder | Group::Gas, Group::Powder) => false,
+            _ if dy > 0 => other.density < self.density,
+            _ if dy < 0 => other.density > self.density,
+            (Group::Gas, Group::Gas) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Sink one cell through whatever is lighter below, or rise through what is denser above
+/// when `dy` is -1. Straight first, then the diagonals in random order.
+fn sink(api: &mut SimAPI, cell: Cell, dy: i32) -> bool {
+    if api.try_displace(0, dy, cell) {
+        return true;
+    }
+    let left_first = ((api.generation() as u32) ^ api.rand_u32()) & 1 == 0;
+    let dirs: [i32; 2] = if left_first { [-1, 1] } else { [1, -1] };
+    dirs.into_iter().any(|dx| api.try_displace(dx, dy, cell))
+}
+
+/// Reset the cell to its material's source temperature, if it has one
+#[inline]
+fn hold_temp(api: &mut SimAPI, p: &MaterialProps) {
+    if let Some(t) = p.source_temperature {
+        api.set_temp(0, 0, t);
+    }
+}
 
 #[inline]
 pub fn props(mat: Material) -> &'static MaterialProps {
-    &PROPS[mat as usize]
+    table::get().props(mat)
+}
+
+/// The material table in use
+#[inline]
+pub(crate) fn table() -> &'static MaterialTable {
+    table::get()
 }
 
 impl Material {
+    /// Material with this id, or Empty if the table doesn't define one
     #[inline]
     pub fn from_id(id: u8) -> Self {
-        if (id as usize) < PROPS.len() { unsafe { std::mem::transmute(id) } } else { Material::Empty }
+        Material::try_from_id(id).unwrap_or(Material::Empty)
+    }
+
+    /// Material with this id, if the table defines one
+    #[inline]
+    pub fn try_from_id(id: u8) -> Option<Self> {
+        table::get().is_defined(id).then_some(Material(id))
     }
 
     #[inline]
     pub fn id(self) -> u8 {
-        self as u8
+        self.0
+    }
+
+    /// Stable name, used to map material ids in saved snapshots
+    pub fn name(self) -> &'static str {
+        &table::get().info(self).name
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        table::get().find(name)
+    }
+
+    /// Number of materials in the table
+    #[inline]
+    pub fn count() -> usize {
+        table::get().len()
+    }
+
+    /// Every material in the table, by id
+    pub fn all() -> impl Iterator<Item = Material> {
+        table::get().materials()
     }
 }
 
+/// Share of its colour fully burnt fuel loses
+const CHARRED: f32 = 0.7;
+
 #[inline]
 pub fn color_of(cell: Cell) -> [u8; 4] {
     let p = props(cell.material);
@@ -301,32 +400,47 @@ pub fn color_of(cell: Cell) -> [u8; 4] {
     }
 
     let v = cell.ra as i16 - 128;
-    let r = (p.colour[0] as i16 + v / p.colour_variation[0]).clamp(0, 255) as u8;
-    let g = (p.colour[1] as i16 + v / p.colour_variation[1]).clamp(0, 255) as u8;
-    let b = (p.colour[2] as i16 + v / p.colour_variation[2]).clamp(0, 255) as u8;
-    [r, g, b, p.colour[3]]
+    // fuel blackens as it burns
+    let shade = if p.flammability > 0.0 { 1.0 - CHARRED * combustion::charring(cell, p) } else { 1.0 };
+    let channel = |c: usize| ((p.colour[c] as i16 + v / p.colour_variation[c]).clamp(0, 255) as f32 * shade) as u8;
+    [channel(0), channel(1), channel(2), p.colour[3]]
 }
 
 #[inline]
 pub fn glow_of(cell: Cell) -> [u8; 4] {
-    props(cell.material).glow
+    let p = props(cell.material);
+    // burning fuel glows like the flames it gives off
+    if p.flammability > 0.0 && combustion::alight(cell) {
+        return props(Material::Fire).glow;
+    }
+    p.glow
 }
 
-pub fn update_cell(cell: Cell, api: SimAPI) {
-    match cell.material {
-        Material::Sand | Material::Ash => powders::update_sand(cell, api),
-        Material::Water => liquids::update_water(cell, api),
-        Material::Lava => liquids::update_lava(cell, api),
-        Material::Acid => liquids::update_acid(cell, api),
-        Material::Smoke => gases::update_smoke(cell, api),
-        Material::Steam => gases::update_steam(cell, api),
-        Material::Fire => fire::update_fire(cell, api),
-        Material::Ember => fire::update_ember(cell, api),
-
-        Material::Stone => solids::update_stone(cell, api),
-        Material::Ice => solids::update_ice(cell, api),
-        Material::Oil => liquids::update_oil(cell, api),
-        Material::Gunpowder => powders::update_gunpowder(cell, api),
-        _ => {} // Wall, Obsidian, Empty — static
+pub fn update_cell(cell: Cell, mut api: SimAPI) {
+    let p = props(cell.material);
+    if p.emissivity > 0.0 {
+        crate::heat::radiate(&mut api, p.emissivity);
+    }
+    if p.strength > 0.0 && crate::pressure::rupture(&mut api, p) {
+        return;
+    }
+    if reactions::react(cell, &mut api) {
+        return;
+    }
+    if p.flammability > 0.0 && combustion::burn(cell, &mut api, p) {
+        return;
+    }
+    match p.behaviour {
+        Behaviour::Powder => powders::update_sand(cell, api),
+        Behaviour::Water => liquids::update_liquid(cell, api),
+        Behaviour::Lava => liquids::update_lava(cell, api),
+        Behaviour::Smoke => gases::update_smoke(cell, api),
+        Behaviour::Steam => gases::update_steam(cell, api),
+        Behaviour::Fire => fire::update_fire(cell, api),
+        Behaviour::Ember => fire::update_ember(cell, api),
+        Behaviour::Stone => solids::update_stone(cell, api),
+        Behaviour::Ice => solids::update_ice(cell, api),
+        Behaviour::Wood => solids::update_wood(cell, api),
+        Behaviour::Static => {}
     }
 }
@@ -0,0 +1,116 @@
+//! Contact reactions from the `[[reaction]]` rows of the material table.
+//!
+//! Each row reads "a cell of A touching B may turn into A' while B turns into B'".
+//! They run before a cell's own update rule, at most one per cell per tick. When one
+//! changes the cell itself its update rule is skipped for the tick.
+
+use super::{Material, table};
+use crate::{Cell, SimAPI};
+use serde::Deserialize;
+
+/// Where the other cell sits, relative to the reacting one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Side {
+    Above,
+    Beside,
+    Below,
+}
+
+impl Side {
+    pub(crate) fn bit(self) -> u8 {
+        match self {
+            Side::Above => 1,
+            Side::Beside => 2,
+            Side::Below => 4,
+        }
+    }
+}
+
+pub(crate) const ANY_SIDE: u8 = 1 | 2 | 4;
+
+/// Largest byproduct radius, keeps reactions within a chunk's reach
+pub(crate) const MAX_BYPRODUCT_RADIUS: u8 = 4;
+
+/// One `[[reaction]]` row, resolved against the table, stored under its `a` material
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Reaction {
+    pub b: Material,
+    pub a_becomes: Material,
+    pub b_becomes: Material,
+    pub rate: u32,
+    pub min_temp: f32,
+    pub max_temp: f32,
+    /// `Side` bits the other cell may be on
+    pub sides: u8,
+    pub byproduct: Option<Material>,
+    pub byproduct_radius: u8,
+}
+
+const NEIGHBOURS: [(i32, i32, Side); 4] = [(0, -1, Side::Above), (-1, 0, Side::Beside), (1, 0, Side::Beside), (0, 1, Side::Below)];
+
+/// Run the first reaction that fires for this cell. Returns true if the cell itself changed.
+pub(super) fn react(cell: Cell, api: &mut SimAPI) -> bool {
+    let rows = table::get().reactions(cell.material);
+    if rows.is_empty() {
+        return false;
+    }
+
+    let temp = api.temp_here();
+    // start from a random neighbour so no direction wins ties
+    let start = api.rand_u32() as usize;
+    for k in 0..NEIGHBOURS.len() {
+        let (dx, dy, side) = NEIGHBOURS[(start + k) % NEIGHBOURS.len()];
+        if !api.in_bounds(dx, dy) {
+            continue;
+        }
+        let other = api.get(dx, dy);
+        for r in rows {
+            if r.b != other.material || r.sides & side.bit() == 0 || temp < r.min_temp || temp > r.max_temp {
+                continue;
+            }
+            if !api.one_in(r.rate) {
+                // could still happen, don't let the chunk fall asleep on it
+                api.keep_awake();
+                continue;
+            }
+            return apply(r, cell, other, dx, dy, api);
+        }
+    }
+    false
+}
+
+fn apply(r: &Reaction, cell: Cell, other: Cell, dx: i32, dy: i32, api: &mut SimAPI) -> bool {
+    // A leaving its cell for B's is a move, the cell keeps its state; likewise for B
+    let a_moves = r.b_becomes == cell.material && r.a_becomes != cell.material;
+    let b_moves = r.a_becomes == other.material && r.b_becomes != other.material;
+    if r.b_becomes != other.material {
+        let c = if a_moves { cell } else { fresh(r.b_becomes, api) };
+        api.set(dx, dy, c);
+    }
+    if r.a_becomes != cell.material {
+        let c = if b_moves { other } else { fresh(r.a_becomes, api) };
+        api.set(0, 0, c);
+    }
+    if a_moves || b_moves {
+        api.carry(dx, dy);
+    }
+
+    if let Some(byproduct) = r.byproduct {
+        let radius = r.byproduct_radius as i32;
+        for by in -radius..=radius {
+            for bx in -radius..=radius {
+                if api.get(bx, by).material == Material::Empty {
+                    let c = fresh(byproduct, api);
+                    api.set(bx, by, c);
+                }
+            }
+        }
+    }
+    r.a_becomes != cell.material
+}
+
+fn fresh(material: Material, api: &mut SimAPI) -> Cell {
+    let ra = if material == Material::Empty { 0 } else { api.rand_u32() as u8 };
+    Cell { material, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 }
+}
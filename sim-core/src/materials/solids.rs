@@ -1,41 +1,49 @@
-use super::Material;
+use super::{Group, Material, combustion, hold_temp, props};
 use crate::{Cell, SimAPI};
 
-pub(super) fn update_ice(_cell: Cell, mut api: SimAPI) {
+/// Share of its fuel wood has burnt when it can no longer hold itself up
+const WEAKENED: f32 = 0.5;
+
+pub(super) fn update_ice(cell: Cell, mut api: SimAPI) {
     api.apply_gravity();
     api.resolve_velocity();
 
-    const MELT_HEAT: u8 = 30;
-    const MELT_RATE: u32 = 50; // 1-in-N chance to melt per tick when hot enough
-    const FREEZE_RATE: u32 = 200; // 1-in-N chance to freeze an adjacent water cell
+    let p = props(cell.material);
 
-    if api.heat_here() > MELT_HEAT && api.rand_u32() % MELT_RATE == 0 {
+    if api.temp_here() > p.melt_point && api.one_in(p.melt_rate) {
         let ra = api.rand_u32() as u8;
-        api.set(0, 0, Cell { material: Material::Water, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+        api.set(0, 0, Cell { material: Material::Water, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
         return;
     }
 
-    // Slowly spread ice to adjacent water
-    if api.rand_u32() % FREEZE_RATE == 0 {
-        for (dx, dy) in [(0i32, -1i32), (-1, 0), (1, 0), (0, 1)] {
-            if api.get(dx, dy).material == Material::Water {
-                let ra = api.rand_u32() as u8;
-                api.set(dx, dy, Cell { material: Material::Ice, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
-                break;
-            }
-        }
-    }
+    // keeps itself frozen unless something warms it faster than that
+    hold_temp(&mut api, p);
 }
 
-pub(super) fn update_stone(_cell: Cell, mut api: SimAPI) {
+pub(super) fn update_stone(cell: Cell, mut api: SimAPI) {
     api.apply_gravity();
     api.resolve_velocity();
 
-    const MELT_HEAT: u8 = 55; // needs to be basically submerged in lava to reach this
-    const MELT_RATE: u32 = 300; // very rare even when hot enough
+    // the built-in melt point needs it basically submerged in lava, and it's rare even then
+    let p = props(cell.material);
 
-    if api.heat_here() > MELT_HEAT && api.rand_u32() % MELT_RATE == 0 {
+    if api.temp_here() > p.melt_point && api.one_in(p.melt_rate) {
         let ra = api.rand_u32() as u8;
-        api.set(0, 0, Cell { material: Material::Lava, ra, rb: 0, clock: 0, vx: 0, vy: 0 });
+        api.set(0, 0, Cell { material: Material::Lava, ra, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 });
+    }
+}
+
+pub(super) fn update_wood(_cell: Cell, mut api: SimAPI) {
+    // burning may have changed it this tick
+    let cell = api.get(0, 0);
+    let p = props(cell.material);
+
+    // burnt far enough, a cell with nothing under it breaks loose and falls
+    if combustion::charring(cell, p) < WEAKENED {
+        return;
+    }
+    let below = api.get(0, 1).material;
+    if matches!(props(below).group, Group::Empty | Group::Gas | Group::Fire) {
+        api.try_move_into(0, 1, cell, &[below]);
     }
 }
@@ -0,0 +1,421 @@
+//! The material table: which ids exist, their names and their props.
+//!
+//! The built-in table is compiled in from `materials.toml`. A different document can be
+//! installed with `load_materials`, as long as that happens before anything reads a
+//! material, since cells and rules hold on to ids from the table they started with.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::combustion::MAX_BURN_DURATION;
+use super::reactions::{ANY_SIDE, MAX_BYPRODUCT_RADIUS, Reaction, Side};
+use super::{Behaviour, CombustionProducts, Group, MAX_COMBUSTION_PRODUCTS, Material, MaterialProps, cold, never, one};
+use crate::chunks::MAX_REACH;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+const BUILTIN: &str = include_str!("../../materials.toml");
+
+/// Names of the built-in materials, indexed by their fixed id
+const BUILTIN_NAMES: [&str; 17] = [
+    "empty",
+    "wall",
+    "sand",
+    "water",
+    "stone",
+    "wood",
+    "fire",
+    "smoke",
+    "ash",
+    "lava",
+    "steam",
+    "obsidian",
+    "acid",
+    "ember",
+    "oil",
+    "ice",
+    "gunpowder",
+];
+
+static TABLE: OnceLock<MaterialTable> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MaterialsError {
+    /// not valid TOML, or a key has the wrong type
+    Parse(String),
+    /// parsed, but doesn't describe a usable table
+    Invalid(String),
+    /// a table is already in use
+    AlreadyLoaded,
+}
+
+impl fmt::Display for MaterialsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaterialsError::Parse(e) => write!(f, "{e}"),
+            MaterialsError::Invalid(e) => write!(f, "{e}"),
+            MaterialsError::AlreadyLoaded => write!(f, "materials are already in use and can no longer be replaced"),
+        }
+    }
+}
+
+impl std::error::Error for MaterialsError {}
+
+#[cfg(feature = "wasm")]
+impl From<MaterialsError> for JsValue {
+    fn from(e: MaterialsError) -> Self {
+        JsValue::from_str(&e.to_string())
+    }
+}
+
+/// What the UI needs to offer a material as a brush
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[derive(Clone, Debug)]
+pub struct MaterialInfo {
+    pub id: u8,
+    pub name: String,
+    pub label: String,
+    pub description: String,
+    /// lowercase group name, as written in the table
+    pub group: String,
+    /// css colour for palette swatches
+    pub swatch: String,
+    pub palette: bool,
+}
+
+#[derive(Deserialize)]
+struct Document {
+    material: Vec<Entry>,
+    #[serde(default)]
+    reaction: Vec<ReactionEntry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    id: u8,
+    name: String,
+    label: Option<String>,
+    #[serde(default)]
+    description: String,
+    #[serde(default = "yes")]
+    palette: bool,
+    swatch: Option<[u8; 3]>,
+    behaviour: Option<Behaviour>,
+    opaque: Option<bool>,
+    rubble: Option<String>,
+    freezes_into: Option<String>,
+    combustion_products: Option<Vec<String>>,
+    #[serde(flatten)]
+    props: MaterialProps,
+    /// anything left over is a misspelt or unsupported key
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReactionEntry {
+    a: String,
+    b: String,
+    a_becomes: Option<String>,
+    b_becomes: Option<String>,
+    #[serde(default = "one")]
+    rate: u32,
+    #[serde(default = "cold")]
+    min_temp: f32,
+    #[serde(default = "never")]
+    max_temp: f32,
+    at: Option<Vec<Side>>,
+    byproduct: Option<String>,
+    #[serde(default = "one")]
+    byproduct_radius: u8,
+}
+
+fn yes() -> bool {
+    true
+}
+
+pub struct MaterialTable {
+    props: Box<[MaterialProps]>,
+    info: Vec<Option<MaterialInfo>>,
+    /// reaction rows by the id of their `a` material
+    reactions: Vec<Vec<Reaction>>,
+    /// heat exchanged per degree of difference between two materials, 256 x 256
+    conductance: Box<[f32]>,
+}
+
+impl MaterialTable {
+    /// The table compiled in from `materials.toml`
+    pub fn builtin() -> MaterialTable {
+        MaterialTable::from_toml(BUILTIN).expect("built-in materials.toml is valid")
+    }
+
+    /// Parse a table from a TOML document laid out like `materials.toml`
+    pub fn from_toml(src: &str) -> Result<MaterialTable, MaterialsError> {
+        let doc: Document = toml::from_str(src).map_err(|e| MaterialsError::Parse(e.to_string()))?;
+        if doc.material.len() > u8::MAX as usize {
+            return Err(MaterialsError::Invalid(format!("at most {} materials are supported", u8::MAX)));
+        }
+
+        let mut info: Vec<Option<MaterialInfo>> = vec![None; 256];
+        let mut props: Vec<Option<MaterialProps>> = vec![None; 256];
+        // resolved once every name is known
+        let mut rubble: Vec<(usize, String)> = Vec::new();
+        let mut frozen: Vec<(usize, String)> = Vec::new();
+        let mut products: Vec<(usize, Vec<String>)> = Vec::new();
+        for entry in doc.material {
+            let invalid = |msg: String| MaterialsError::Invalid(format!("material '{}': {msg}", entry.name));
+
+            if let Some(key) = entry.unknown.keys().next() {
+                return Err(invalid(format!("unknown key '{key}'")));
+            }
+            if entry.name.is_empty() || entry.name.len() > u8::MAX as usize || entry.name.chars().any(|c| c.is_whitespace() || c.is_uppercase()) {
+                return Err(invalid("names must be lowercase, without spaces and at most 255 bytes".into()));
+            }
+            if entry.props.colour_variation.contains(&0) {
+                return Err(invalid("colour_variation can't be 0".into()));
+            }
+            // more would let a tick's exchange overshoot and oscillate
+            if !(1.0..).contains(&entry.props.heat_capacity) || !(0.0..=1.0).contains(&entry.props.thermal_conductivity) {
+                return Err(invalid("heat_capacity must be at least 1 and thermal_conductivity 0 to 1".into()));
+            }
+            if !(0.0..=1.0).contains(&entry.props.emissivity) {
+                return Err(invalid("emissivity must be 0 to 1".into()));
+            }
+            if !(0.0..=1.0).contains(&entry.props.wind_response) {
+                return Err(invalid("wind_response must be 0 to 1".into()));
+            }
+            if !(0.0..=1.0).contains(&entry.props.restitution) {
+                return Err(invalid("restitution must be 0 to 1".into()));
+            }
+            if entry.props.buoyant && entry.props.group != Group::Solid {
+                return Err(invalid("only solids can be buoyant".into()));
+            }
+            if !(0.0..=1.0).contains(&entry.props.flammability) {
+                return Err(invalid("flammability must be 0 to 1".into()));
+            }
+            if entry.props.flammability > 0.0 && matches!(entry.props.group, Group::Empty | Group::Fire) {
+                return Err(invalid("empty and fire can't burn".into()));
+            }
+            if entry.props.burn_duration > MAX_BURN_DURATION {
+                return Err(invalid(format!("burn_duration can be at most {MAX_BURN_DURATION}")));
+            }
+            if entry.combustion_products.as_ref().is_some_and(|names| names.is_empty() || names.len() > MAX_COMBUSTION_PRODUCTS) {
+                return Err(invalid(format!("combustion_products lists 1 to {MAX_COMBUSTION_PRODUCTS} materials")));
+            }
+            if !(0..=MAX_REACH as i32).contains(&(entry.props.terminal_velocity as i32)) {
+                return Err(invalid(format!("terminal_velocity must be 0 to {MAX_REACH}")));
+            }
+            if entry.props.dispersion as u32 > MAX_REACH {
+                return Err(invalid(format!("dispersion can be at most {MAX_REACH}")));
+            }
+            // it moves up to terminal_velocity cells along each axis, then looks one cell past
+            // where it stopped, steps one more diagonally or spreads up to dispersion sideways
+            let reach = entry.props.terminal_velocity as u32 + (entry.props.dispersion as u32).max(1);
+            if reach > MAX_REACH {
+                return Err(invalid(format!("terminal_velocity and dispersion together reach {reach} cells, at most {MAX_REACH}")));
+            }
+            if entry.props.viscosity == 0 {
+                return Err(invalid("viscosity must be at least 1".into()));
+            }
+            if entry.props.density <= 0.0 {
+                return Err(invalid("density must be positive".into()));
+            }
+            if entry.props.strength < 0.0 {
+                return Err(invalid("strength can't be negative".into()));
+            }
+            let id = entry.id as usize;
+            if let Some(other) = &info[id] {
+                return Err(invalid(format!("id {id} is already used by '{}'", other.name)));
+            }
+            if info.iter().flatten().any(|i| i.name == entry.name) {
+                return Err(invalid("defined twice".into()));
+            }
+
+            let mut p = entry.props;
+            p.behaviour = entry.behaviour.unwrap_or_else(|| Behaviour::default_for(p.group));
+            p.opaque = entry.opaque.unwrap_or_else(|| p.group.opaque_by_default());
+            let [r, g, b] = entry.swatch.unwrap_or([p.colour[0], p.colour[1], p.colour[2]]);
+            info[id] = Some(MaterialInfo {
+                id: entry.id,
+                label: entry.label.unwrap_or_else(|| entry.name.clone()),
+                name: entry.name,
+                description: entry.description,
+                group: group_name(p.group).into(),
+                swatch: format!("#{r:02x}{g:02x}{b:02x}"),
+                palette: entry.palette,
+            });
+            props[id] = Some(p);
+            if let Some(name) = entry.rubble {
+                rubble.push((id, name));
+            }
+            if let Some(name) = entry.freezes_into {
+                frozen.push((id, name));
+            }
+            if let Some(names) = entry.combustion_products {
+                products.push((id, names));
+            }
+        }
+
+        for (id, name) in BUILTIN_NAMES.iter().enumerate() {
+            if info[id].as_ref().is_none_or(|i| i.name != *name) {
+                return Err(MaterialsError::Invalid(format!("built-in material '{name}' must be defined with id {id}")));
+            }
+        }
+
+        // undefined ids are never stored in cells, give them empty's props so lookups stay total
+        let empty = props[0].unwrap();
+        let props: Box<[MaterialProps]> = props.into_iter().map(|p| p.unwrap_or(empty)).collect();
+        // harmonic mean, so heat passes no faster than the worse conductor of the pair allows
+        let conductance = (0..256 * 256)
+            .map(|i| {
+                let (a, b) = (props[i / 256].thermal_conductivity, props[i % 256].thermal_conductivity);
+                if a + b > 0.0 { 2.0 * a * b / (a + b) } else { 0.0 }
+            })
+            .collect();
+        let mut table = MaterialTable { props, info, reactions: vec![Vec::new(); 256], conductance };
+
+        for (id, name) in rubble {
+            let m = table
+                .find(&name)
+                .ok_or_else(|| MaterialsError::Invalid(format!("material '{}': rubble is unknown material '{name}'", table.info[id].as_ref().unwrap().name)))?;
+            table.props[id].rubble = m;
+        }
+
+        for (id, name) in frozen {
+            let invalid = |msg: String| MaterialsError::Invalid(format!("material '{}': {msg}", table.info[id].as_ref().unwrap().name));
+            let m = table.find(&name).ok_or_else(|| invalid(format!("freezes_into is unknown material '{name}'")))?;
+            // it sets at the temperature the solid melts at
+            if table.props[m.id() as usize].melt_point == f32::INFINITY {
+                return Err(invalid(format!("freezes_into material '{name}' has no melt_point")));
+            }
+            table.props[id].freezes_into = m;
+        }
+
+        for (id, names) in products {
+            let materials = names
+                .iter()
+                .map(|name| {
+                    table
+                        .find(name)
+                        .ok_or_else(|| MaterialsError::Invalid(format!("material '{}': combustion product is unknown material '{name}'", table.info[id].as_ref().unwrap().name)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            table.props[id].combustion_products = CombustionProducts::new(&materials).expect("length checked above");
+        }
+
+        for (n, entry) in doc.reaction.into_iter().enumerate() {
+            let invalid = |msg: String| MaterialsError::Invalid(format!("reaction {} ({} + {}): {msg}", n + 1, entry.a, entry.b));
+            let resolve = |name: &str| table.find(name).ok_or_else(|| invalid(format!("unknown material '{name}'")));
+
+            let a = resolve(&entry.a)?;
+            let b = resolve(&entry.b)?;
+            if a == Material::Empty {
+                return Err(invalid("empty never updates, so it can't be the reacting material".into()));
+            }
+            if entry.min_temp > entry.max_temp {
+                return Err(invalid("min_temp is above max_temp".into()));
+            }
+            if !(1..=MAX_BYPRODUCT_RADIUS).contains(&entry.byproduct_radius) {
+                return Err(invalid(format!("byproduct_radius must be 1 to {MAX_BYPRODUCT_RADIUS}")));
+            }
+            let reaction = Reaction {
+                b,
+                a_becomes: entry.a_becomes.as_deref().map(resolve).transpose()?.unwrap_or(a),
+                b_becomes: entry.b_becomes.as_deref().map(resolve).transpose()?.unwrap_or(b),
+                rate: entry.rate,
+                min_temp: entry.min_temp,
+                max_temp: entry.max_temp,
+                sides: entry.at.map_or(ANY_SIDE, |at| at.iter().fold(0, |bits, s| bits | s.bit())),
+                byproduct: entry.byproduct.as_deref().map(resolve).transpose()?,
+                byproduct_radius: entry.byproduct_radius,
+            };
+            if reaction.sides == 0 {
+                return Err(invalid("'at' lists no sides".into()));
+            }
+            table.reactions[a.0 as usize].push(reaction);
+        }
+        Ok(table)
+    }
+
+    #[inline]
+    pub fn props(&self, mat: Material) -> &MaterialProps {
+        &self.props[mat.0 as usize]
+    }
+
+    #[inline]
+    pub fn is_defined(&self, id: u8) -> bool {
+        self.info[id as usize].is_some()
+    }
+
+    pub fn info(&self, mat: Material) -> &MaterialInfo {
+        self.info[mat.0 as usize].as_ref().expect("materials only hold defined ids")
+    }
+
+    pub fn find(&self, name: &str) -> Option<Material> {
+        self.info.iter().flatten().find(|i| i.name == name).map(|i| Material(i.id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.info.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every material in the table, by id
+    pub fn materials(&self) -> impl Iterator<Item = Material> + '_ {
+        self.info.iter().flatten().map(|i| Material(i.id))
+    }
+
+    /// Reaction rows with `mat` as the reacting material, in table order
+    #[inline]
+    pub(crate) fn reactions(&self, mat: Material) -> &[Reaction] {
+        &self.reactions[mat.0 as usize]
+    }
+
+    /// Heat exchanged between neighbouring cells of these materials per degree of difference
+    #[inline]
+    pub(crate) fn conductance(&self, a: Material, b: Material) -> f32 {
+        self.conductance[a.0 as usize * 256 + b.0 as usize]
+    }
+
+    /// Number of reaction rows in the table
+    pub fn reaction_count(&self) -> usize {
+        self.reactions.iter().map(Vec::len).sum()
+    }
+}
+
+fn group_name(group: Group) -> &'static str {
+    match group {
+        Group::Empty => "empty",
+        Group::Powder => "powder",
+        Group::Liquid => "liquid",
+        Group::Solid => "solid",
+        Group::Gas => "gas",
+        Group::Fire => "fire",
+    }
+}
+
+/// The table in use, the built-in one unless `load_materials` installed another first
+#[inline]
+pub(crate) fn get() -> &'static MaterialTable {
+    TABLE.get_or_init(MaterialTable::builtin)
+}
+
+/// Use the materials in a TOML document instead of the built-in ones. Only possible
+/// before the first simulation is created or any material is looked up.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn load_materials(src: &str) -> Result<(), MaterialsError> {
+    let table = MaterialTable::from_toml(src)?;
+    TABLE.set(table).map_err(|_| MaterialsError::AlreadyLoaded)
+}
+
+/// Every material in the table in use, by id
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn materials() -> Vec<MaterialInfo> {
+    get().info.iter().flatten().cloned().collect()
+}
@@ -0,0 +1,203 @@
+//! Oxygen, one value per cell, as a share of what fresh air holds.
+//!
+//! It lives in the air: empty space, gases and flames. Flames and embers burn what is in
+//! their own cell and burning fuel what is in the air beside it. Each tick it spreads
+//! between touching air cells, and the air a fire churns up mixes every connected body of
+//! it towards its mean, or towards fresh air if it reaches the edge of the grid, which
+//! opens onto the outside. So a fire in the open keeps drawing in fresh air, while one
+//! sealed in a box works through what the box holds and goes out.
+//!
+//! Powders, liquids and solids hold none of their own and read as fresh air, which is
+//! what they leave behind when they move through the air and swap places with it.
+
+use crate::chunks::CHUNK_SIZE;
+use crate::{Group, Material, Simulation, par, props};
+use std::collections::HashSet;
+
+/// Oxygen in fresh air, and in the outside beyond the edges of the grid
+pub const AMBIENT_OXYGEN: f32 = 1.0;
+
+/// Below this share of fresh air's oxygen nothing burns
+pub(crate) const SMOTHERED: f32 = 0.3;
+
+/// Oxygen a flame burns a tick
+pub(crate) const FLAME_USE: f32 = 0.02;
+
+/// Oxygen a burning cell of fuel takes from the air beside it a tick
+pub(crate) const FUEL_USE: f32 = 0.1;
+
+/// Share of the difference to each air neighbour that moves per tick
+const SPREAD: f32 = 0.2;
+
+/// Share of the difference to its body's mean, or to fresh air, a cell makes up per tick
+const MIX: f32 = 0.05;
+
+/// Cells this close to fresh air are snapped to it, so settled chunks can stop spreading
+const SNAP: f32 = 0.001;
+
+/// Whether cells of this material are air, and so hold and pass on oxygen
+#[inline]
+pub(crate) fn holds_oxygen(m: Material) -> bool {
+    matches!(props(m).group, Group::Empty | Group::Gas | Group::Fire)
+}
+
+impl Simulation {
+    pub(crate) fn diffuse_oxygen(&mut self) {
+        // chunks of fresh air with nothing depleted around them stay that way, skip them
+        let targets = self.chunks.oxygen_targets();
+        if targets.is_empty() {
+            return;
+        }
+
+        let w = self.width as usize;
+        let h = self.height as usize;
+        let mut air = [false; 256];
+        for m in Material::all() {
+            air[m.id() as usize] = holds_oxygen(m);
+        }
+        self.mix_air(&targets, &air);
+
+        // each row of chunks owns its band of oxygen_next, so bands spread in parallel
+        let band_len = w * CHUNK_SIZE as usize;
+        let mut bands: Vec<(u32, &mut [f32])> = self
+            .oxygen_next
+            .chunks_mut(band_len)
+            .zip(0..)
+            .filter(|&(_, cy)| targets.iter().any(|t| t.1 == cy))
+            .map(|(band, cy)| (cy, band))
+            .collect();
+        let (oxygen, cells, chunks, width, height) = (&self.oxygen, &self.cells, &self.chunks, self.width, self.height);
+
+        par::for_each(self.threads, &mut bands, |(cy, band)| {
+            let offset = *cy as usize * band_len;
+            for &(cx, _) in targets.iter().filter(|t| t.1 == *cy) {
+                let (x0, y0, x1, y1) = chunks.bounds(cx, *cy, width, height);
+                let mut depleted = false;
+                for y in y0 as usize..y1 as usize {
+                    for x in x0 as usize..x1 as usize {
+                        let i = y * w + x;
+                        if !air[cells[i].material.id() as usize] {
+                            band[i - offset] = AMBIENT_OXYGEN;
+                            continue;
+                        }
+                        let o = oxygen[i];
+                        let mut flow = 0.0;
+                        for (j, open) in [(i.wrapping_sub(w), y > 0), (i + w, y + 1 < h), (i.wrapping_sub(1), x > 0), (i + 1, x + 1 < w)] {
+                            if !open {
+                                // fresh air comes in from outside
+                                flow += AMBIENT_OXYGEN - o;
+                            } else if air[cells[j].material.id() as usize] {
+                                flow += oxygen[j] - o;
+                            }
+                        }
+                        let mut next = (o + flow * SPREAD).min(AMBIENT_OXYGEN);
+                        if AMBIENT_OXYGEN - next < SNAP {
+                            next = AMBIENT_OXYGEN;
+                        }
+                        band[i - offset] = next;
+                        depleted |= next != AMBIENT_OXYGEN;
+                        // fuel waiting on air to burn in only needs to look again once there's enough
+                        if (next < SMOTHERED) != (o < SMOTHERED) {
+                            chunks.wake(x as i32, y as i32);
+                        }
+                    }
+                }
+                chunks.set_depleted(cx, *cy, depleted);
+            }
+        });
+
+        // only spread chunks changed, copy those back
+        for &(cx, cy) in &targets {
+            let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+            for y in y0 as usize..y1 as usize {
+                let row = y * w + x0 as usize..y * w + x1 as usize;
+                self.oxygen[row.clone()].copy_from_slice(&self.oxygen_next[row]);
+            }
+        }
+    }
+}
+
+impl Simulation {
+    /// Pull every connected body of air in the target chunks towards its mean oxygen, or
+    /// towards fresh air if it reaches the edge of the grid. A body is followed into the
+    /// chunks around the targets too, so a sealed room mixes as a whole however much of it
+    /// is settled, and chunks it drains are marked so oxygen spreads through them again
+    fn mix_air(&mut self, targets: &[(u32, u32)], air: &[bool; 256]) {
+        let w = self.width as usize;
+        let h = self.height as usize;
+
+        // which chunks are targets, and which cells are air a fill may enter
+        let mut inside = vec![false; (self.chunks.cols * self.chunks.rows) as usize];
+        for &(cx, cy) in targets {
+            inside[(cy * self.chunks.cols + cx) as usize] = true;
+        }
+        let size = CHUNK_SIZE as usize;
+        let cols = self.chunks.cols as usize;
+        let inside = |x: usize, y: usize| inside[y / size * cols + x / size];
+
+        let mut stack = Vec::new();
+        let mut body = Vec::new();
+        // air outside the targets where an open body stopped filling
+        let mut gates = HashSet::new();
+        for &(cx, cy) in targets {
+            let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+            for y in y0 as usize..y1 as usize {
+                for x in x0 as usize..x1 as usize {
+                    if !air[self.cells[y * w + x].material.id() as usize] || !self.fill(y * w + x) {
+                        continue;
+                    }
+
+                    // flood fill the body
+                    stack.push((x, y));
+                    body.clear();
+                    let mut sum = 0.0;
+                    let mut open = false;
+                    while let Some((x, y)) = stack.pop() {
+                        let j = y * w + x;
+                        body.push(j);
+                        sum += self.oxygen[j];
+                        open |= x == 0 || y == 0 || x + 1 == w || y + 1 == h;
+                        for (nx, ny, ok) in [(x, y.wrapping_sub(1), y > 0), (x, y + 1, y + 1 < h), (x.wrapping_sub(1), y, x > 0), (x + 1, y, x + 1 < w)] {
+                            if !ok {
+                                continue;
+                            }
+                            let n = ny * w + nx;
+                            if !air[self.cells[n].material.id() as usize] {
+                                continue;
+                            }
+                            if inside(nx, ny) || !open {
+                                if self.fill(n) {
+                                    stack.push((nx, ny));
+                                } else if !open && gates.contains(&n) {
+                                    open = true;
+                                }
+                            } else if self.fill(n) {
+                                // past the targets the air of an open body is all fresh, so
+                                // leave it be and just let later bodies know it leads outside
+                                gates.insert(n);
+                            }
+                        }
+                    }
+
+                    let mean = if open { AMBIENT_OXYGEN } else { sum / body.len() as f32 };
+                    for &j in &body {
+                        let o = self.oxygen[j];
+                        if o == mean {
+                            continue;
+                        }
+                        let next = o + (mean - o) * MIX;
+                        self.oxygen[j] = next;
+                        let (x, y) = (j % w, j / w);
+                        if (next < SMOTHERED) != (o < SMOTHERED) {
+                            self.chunks.wake(x as i32, y as i32);
+                        }
+                        if !inside(x, y) {
+                            self.chunks.deplete(x as i32, y as i32);
+                        }
+                    }
+                }
+            }
+        }
+        self.clear_filled();
+    }
+}
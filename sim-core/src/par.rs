@@ -0,0 +1,36 @@
+//! Minimal work sharing for `step` on native builds. Wasm has no threads, so there
+//! everything runs on the calling thread.
+
+/// Call `f` on every item, spread over up to `threads` threads. Items are handed out
+/// one at a time as threads free up, so `f` must not care which order they run in.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn for_each<T: Send>(threads: usize, items: &mut [T], f: impl Fn(&mut T) + Sync) {
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        items.iter_mut().for_each(f);
+        return;
+    }
+
+    let queue = std::sync::Mutex::new(items.iter_mut());
+    let work = || {
+        loop {
+            // the guard is dropped before `f` runs, other threads keep taking items
+            let next = queue.lock().unwrap().next();
+            match next {
+                Some(item) => f(item),
+                None => break,
+            }
+        }
+    };
+    std::thread::scope(|s| {
+        for _ in 1..threads {
+            s.spawn(work);
+        }
+        work();
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn for_each<T: Send>(_threads: usize, items: &mut [T], f: impl Fn(&mut T) + Sync) {
+    items.iter_mut().for_each(f);
+}
@@ -0,0 +1,255 @@
+//! Air pressure, one value per cell, measured over the pressure of the air around the scene.
+//!
+//! Pressure lives in fluids: empty space, gases, flames and liquids. Gas released into
+//! them, like water flashing to steam, raises it. Each tick it spreads between touching
+//! fluid cells, and every connected body of fluid is pulled towards its mean pressure, so
+//! a sealed room evens out within a few ticks the way sound would cross it. Bodies that
+//! reach the edge of the grid open onto the outside and are pulled towards ambient
+//! instead. Powders and solids hold none, so a sealed box keeps whatever builds up in it.
+//!
+//! A pressure difference across a cell pushes it: anything that moves gains velocity
+//! towards the lower side, which is also how gases flow out of high pressure. Solids with
+//! a strength break once one side of them is that much above another.
+//!
+//! The field is per cell rather than on a coarser grid since a coarse block straddling a
+//! one-cell wall couldn't tell which side of it its pressure is on.
+
+use crate::chunks::CHUNK_SIZE;
+use crate::{Cell, Group, Material, MaterialProps, SUBCELL, SimAPI, Simulation, par, props};
+
+/// Spreading passes per tick
+const PASSES: usize = 2;
+
+/// Share of the difference to each air neighbour that moves per pass
+const SPREAD: f32 = 0.2;
+
+/// Share of the difference to its body's mean pressure a cell makes up per tick
+const EVEN_OUT: f32 = 0.5;
+
+/// Fraction lost per tick, through gaps too small to draw
+const LEAK: f32 = 0.0005;
+
+/// Pressures this close to ambient are snapped to it, so settled chunks can stop spreading
+const SNAP: f32 = 0.01;
+
+/// Velocity gained per unit of pressure difference across a cell, in cells per tick
+const PUSH: f32 = 0.125;
+
+/// Fastest a broken piece flies off
+const BURST_SPEED: i8 = 3;
+
+const NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+/// Whether cells of this material are fluid, and so hold and pass on pressure
+#[inline]
+pub(crate) fn holds_pressure(m: Material) -> bool {
+    matches!(props(m).group, Group::Empty | Group::Gas | Group::Fire | Group::Liquid)
+}
+
+/// Pressure of the fluid at the offset, None if there is none there. Off the grid is the
+/// outside, at ambient.
+#[inline]
+fn air_pressure(api: &SimAPI, dx: i32, dy: i32) -> Option<f32> {
+    if !api.in_bounds(dx, dy) {
+        return Some(0.0);
+    }
+    holds_pressure(api.get(dx, dy).material).then(|| api.get_pressure(dx, dy))
+}
+
+/// Velocity the pressure difference across this cell adds this tick, as (vx, vy)
+pub(crate) fn push(api: &SimAPI) -> (i16, i16) {
+    if !api.pressurized() {
+        return (0, 0);
+    }
+    let axis = |a: Option<f32>, b: Option<f32>| match (a, b) {
+        (Some(a), Some(b)) => ((a - b) * PUSH * SUBCELL as f32) as i16,
+        _ => 0,
+    };
+    (axis(air_pressure(api, -1, 0), air_pressure(api, 1, 0)), axis(air_pressure(api, 0, -1), air_pressure(api, 0, 1)))
+}
+
+/// Break this cell if the pressure on one side of it beats another by more than its
+/// strength. The pieces fly off towards the low side. Returns true if it broke.
+pub(crate) fn rupture(api: &mut SimAPI, p: &MaterialProps) -> bool {
+    if !api.pressurized() {
+        return false;
+    }
+    let mut high: Option<(f32, i32, i32)> = None;
+    let mut low: Option<(f32, i32, i32)> = None;
+    for (dx, dy) in NEIGHBOURS {
+        if let Some(pressure) = air_pressure(api, dx, dy) {
+            if high.is_none_or(|h| pressure > h.0) {
+                high = Some((pressure, dx, dy));
+            }
+            if low.is_none_or(|l| pressure < l.0) {
+                low = Some((pressure, dx, dy));
+            }
+        }
+    }
+    let (Some((high, hx, hy)), Some((low, lx, ly))) = (high, low) else {
+        return false;
+    };
+    if high - low <= p.strength {
+        return false;
+    }
+
+    let cell = if p.rubble == Material::Empty {
+        Cell::empty_with_clock(0)
+    } else {
+        let speed = props(p.rubble).terminal_velocity.clamp(0, BURST_SPEED) as i16 * SUBCELL;
+        let ra = api.rand_u32() as u8;
+        Cell { material: p.rubble, ra, rb: 0, clock: 0, vx: (lx - hx).signum() as i16 * speed, vy: (ly - hy).signum() as i16 * speed, sx: 0, sy: 0 }
+    };
+    api.set(0, 0, cell);
+    true
+}
+
+impl Simulation {
+    pub(crate) fn diffuse_pressure(&mut self) {
+        // chunks at ambient with nothing pressurized around them stay that way, skip them
+        let targets = self.chunks.pressure_targets();
+        if targets.is_empty() {
+            return;
+        }
+
+        let w = self.width as usize;
+        let h = self.height as usize;
+        let mut fluid = [false; 256];
+        for m in Material::all() {
+            fluid[m.id() as usize] = holds_pressure(m);
+        }
+        // gas released during the tick joins in first; it only lands in pressurized chunks
+        for &(cx, cy) in &targets {
+            let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+            for y in y0 as usize..y1 as usize {
+                for i in y * w + x0 as usize..y * w + x1 as usize {
+                    self.pressure[i] += std::mem::take(&mut self.released[i]);
+                }
+            }
+        }
+        self.even_out(&targets, &fluid);
+
+        let band_len = w * CHUNK_SIZE as usize;
+        for pass in 0..PASSES {
+            let last = pass + 1 == PASSES;
+            // each row of chunks owns its band of pressure_next, so bands spread in parallel
+            let mut bands: Vec<(u32, &mut [f32])> = self
+                .pressure_next
+                .chunks_mut(band_len)
+                .zip(0..)
+                .filter(|&(_, cy)| targets.iter().any(|t| t.1 == cy))
+                .map(|(band, cy)| (cy, band))
+                .collect();
+            let (pressure, cells, chunks, width, height) = (&self.pressure, &self.cells, &self.chunks, self.width, self.height);
+
+            par::for_each(self.threads, &mut bands, |(cy, band)| {
+                let offset = *cy as usize * band_len;
+                for &(cx, _) in targets.iter().filter(|t| t.1 == *cy) {
+                    let (x0, y0, x1, y1) = chunks.bounds(cx, *cy, width, height);
+                    let mut pressurized = false;
+                    for y in y0 as usize..y1 as usize {
+                        for x in x0 as usize..x1 as usize {
+                            let i = y * w + x;
+                            if !fluid[cells[i].material.id() as usize] {
+                                band[i - offset] = 0.0;
+                                continue;
+                            }
+                            let p = pressure[i];
+                            let mut flow = 0.0;
+                            for (j, open) in [(i.wrapping_sub(w), y > 0), (i + w, y + 1 < h), (i.wrapping_sub(1), x > 0), (i + 1, x + 1 < w)] {
+                                if !open {
+                                    // the outside soaks up whatever reaches the edge
+                                    flow -= p;
+                                } else if fluid[cells[j].material.id() as usize] {
+                                    flow += pressure[j] - p;
+                                }
+                            }
+                            let mut next = p + flow * SPREAD;
+                            if last {
+                                next -= next * LEAK;
+                            }
+                            if next.abs() < SNAP {
+                                next = 0.0;
+                            }
+                            band[i - offset] = next;
+                            pressurized |= next != 0.0;
+                            // whole units are fine enough for the rules that read it
+                            if next.floor() != p.floor() {
+                                chunks.wake(x as i32, y as i32);
+                            }
+                        }
+                    }
+                    if last {
+                        chunks.set_pressurized(cx, *cy, pressurized);
+                    }
+                }
+            });
+
+            // only spread chunks changed, copy those back
+            for &(cx, cy) in &targets {
+                let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+                for y in y0 as usize..y1 as usize {
+                    let row = y * w + x0 as usize..y * w + x1 as usize;
+                    self.pressure[row.clone()].copy_from_slice(&self.pressure_next[row]);
+                }
+            }
+        }
+    }
+
+    /// Pull every connected body of fluid in the target chunks towards its mean pressure,
+    /// or towards ambient if it reaches the edge of the grid
+    fn even_out(&mut self, targets: &[(u32, u32)], fluid: &[bool; 256]) {
+        let w = self.width as usize;
+        let h = self.height as usize;
+
+        // which chunks are targets, the fill stays inside them
+        let mut inside = vec![false; (self.chunks.cols * self.chunks.rows) as usize];
+        for &(cx, cy) in targets {
+            inside[(cy * self.chunks.cols + cx) as usize] = true;
+        }
+        let size = CHUNK_SIZE as usize;
+        let cols = self.chunks.cols as usize;
+        let inside = |x: usize, y: usize| inside[y / size * cols + x / size];
+
+        let mut stack = Vec::new();
+        let mut body = Vec::new();
+        for &(cx, cy) in targets {
+            let (x0, y0, x1, y1) = self.chunks.bounds(cx, cy, self.width, self.height);
+            for y in y0 as usize..y1 as usize {
+                for x in x0 as usize..x1 as usize {
+                    if !fluid[self.cells[y * w + x].material.id() as usize] || !self.fill(y * w + x) {
+                        continue;
+                    }
+
+                    // flood fill the body
+                    stack.push((x, y));
+                    body.clear();
+                    let mut sum = 0.0;
+                    let mut open = false;
+                    while let Some((x, y)) = stack.pop() {
+                        let j = y * w + x;
+                        body.push((x, y));
+                        sum += self.pressure[j];
+                        open |= x == 0 || y == 0 || x + 1 == w || y + 1 == h;
+                        for (nx, ny, ok) in [(x, y.wrapping_sub(1), y > 0), (x, y + 1, y + 1 < h), (x.wrapping_sub(1), y, x > 0), (x + 1, y, x + 1 < w)] {
+                            if ok && inside(nx, ny) && fluid[self.cells[ny * w + nx].material.id() as usize] && self.fill(ny * w + nx) {
+                                stack.push((nx, ny));
+                            }
+                        }
+                    }
+
+                    let mean = if open { 0.0 } else { sum / body.len() as f32 };
+                    for &(x, y) in &body {
+                        let p = self.pressure[y * w + x];
+                        let next = p + (mean - p) * EVEN_OUT;
+                        self.pressure[y * w + x] = next;
+                        if next.floor() != p.floor() {
+                            self.chunks.wake(x as i32, y as i32);
+                        }
+                    }
+                }
+            }
+        }
+        self.clear_filled();
+    }
+}
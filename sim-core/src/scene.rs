@@ -0,0 +1,128 @@
+//! Plain-text scene files, for reproducible setups outside the browser.
+//!
+//! One command per line, `#` starts a comment. Materials are given by name or id.
+//! `size` must come before anything is painted; without it the grid is 480x270.
+//!
+//! ```text
+//! size 200 120
+//! seed 42
+//! rect 0 110 199 119 stone   # x0 y0 x1 y1, corners inclusive
+//! circle 100 40 12 sand      # cx cy radius
+//! cell 5 5 fire
+//! wind 0.5 0                 # steady wind vx vy in cells per tick, optionally
+//! wind -1 0 0 0 99 59         # followed by x0 y0 x1 y1 to set it for a region only
+//! step 30                    # run ticks mid-scene, e.g. to let a layer settle
+//! ```
+
+use crate::{DEFAULT_SEED, Material, Simulation};
+use std::fmt;
+
+const DEFAULT_WIDTH: u32 = 480;
+const DEFAULT_HEIGHT: u32 = 270;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SceneError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+fn parse_material(s: &str) -> Option<Material> {
+    match s.parse::<u8>() {
+        Ok(id) => Material::try_from_id(id),
+        Err(_) => Material::from_name(&s.to_ascii_lowercase()),
+    }
+}
+
+impl Simulation {
+    /// Build a simulation from a scene file. `seed` overrides any `seed` line in the scene.
+    pub fn from_scene(src: &str, seed: Option<u64>) -> Result<Simulation, SceneError> {
+        let mut size = (DEFAULT_WIDTH, DEFAULT_HEIGHT);
+        let mut scene_seed = DEFAULT_SEED;
+        let mut sim: Option<Simulation> = None;
+
+        for (n, raw) in src.lines().enumerate() {
+            let line = n + 1;
+            let err = |message: String| SceneError { line, message };
+
+            let text = raw.split('#').next().unwrap_or("").trim();
+            let mut words = text.split_whitespace();
+            let Some(cmd) = words.next() else {
+                continue;
+            };
+            let args: Vec<&str> = words.collect();
+
+            let nums = |count: usize| -> Result<Vec<u32>, SceneError> {
+                if args.len() < count {
+                    return Err(err(format!("'{cmd}' expects {count} numbers")));
+                }
+                args[..count].iter().map(|a| a.parse::<u32>().map_err(|_| err(format!("'{a}' is not a number")))).collect()
+            };
+            let material = |at: usize| -> Result<u8, SceneError> {
+                let name = args.get(at).ok_or_else(|| err(format!("'{cmd}' expects a material")))?;
+                parse_material(name).map(Material::id).ok_or_else(|| err(format!("unknown material '{name}'")))
+            };
+
+            match cmd {
+                "size" | "seed" if sim.is_some() => return Err(err(format!("'{cmd}' must come before anything is painted"))),
+                "size" => {
+                    let v = nums(2)?;
+                    if v[0] == 0 || v[1] == 0 {
+                        return Err(err("size must be non-zero".into()));
+                    }
+                    // the grid's cell count has to fit a u32, as it does for a loaded save
+                    if v[0].checked_mul(v[1]).is_none() {
+                        return Err(err(format!("size {}x{} is too large", v[0], v[1])));
+                    }
+                    size = (v[0], v[1]);
+                }
+                "seed" => {
+                    let a = args.first().ok_or_else(|| err("'seed' expects a number".into()))?;
+                    scene_seed = a.parse().map_err(|_| err(format!("'{a}' is not a number")))?;
+                }
+                _ => {
+                    let s = sim.get_or_insert_with(|| Simulation::with_seed(size.0, size.1, seed.unwrap_or(scene_seed)));
+                    match cmd {
+                        "rect" => {
+                            let v = nums(4)?;
+                            s.paint_rect(v[0], v[1], v[2], v[3], material(4)?);
+                        }
+                        "circle" => {
+                            let v = nums(3)?;
+                            s.paint_circle(v[0], v[1], v[2], material(3)?);
+                        }
+                        "cell" => {
+                            let v = nums(2)?;
+                            s.set_cell(v[0], v[1], material(2)?);
+                        }
+                        "wind" => {
+                            let speed = |i: usize| -> Result<f32, SceneError> { args[i].parse().map_err(|_| err(format!("'{}' is not a number", args[i]))) };
+                            match args.len() {
+                                2 => s.set_wind(speed(0)?, speed(1)?),
+                                6 => {
+                                    let r = args[2..]
+                                        .iter()
+                                        .map(|a| a.parse::<u32>().map_err(|_| err(format!("'{a}' is not a number"))))
+                                        .collect::<Result<Vec<_>, _>>()?;
+                                    s.set_wind_rect(r[0], r[1], r[2], r[3], speed(0)?, speed(1)?);
+                                }
+                                _ => return Err(err("'wind' expects vx vy, optionally followed by x0 y0 x1 y1".into())),
+                            }
+                        }
+                        "step" => s.step(nums(1)?[0]),
+                        _ => return Err(err(format!("unknown command '{cmd}'"))),
+                    }
+                }
+            }
+        }
+
+        Ok(sim.unwrap_or_else(|| Simulation::with_seed(size.0, size.1, seed.unwrap_or(scene_seed))))
+    }
+}
@@ -0,0 +1,261 @@
+//! Binary save/load of the full simulation state.
+//!
+//! Layout, all integers little-endian:
+//!
+//! ```text
+//! magic       b"SSIM"
+//! version     u16
+//! width       u32
+//! height      u32
+//! generation  u8
+//! frame       u32
+//! rng         u64
+//! materials   u8 count, then per entry: id u8, name length u8, name bytes
+//! cells       width * height records of material, ra, rb, clock (u8), vx, vy (i16) and
+//!             sx, sy (i8); before version 6 vx and vy were whole cells per tick in one
+//!             byte each, with no sx and sy
+//! heat        version 3+: width * height f32 temperatures in degrees Celsius,
+//!             before that width * height bytes of the old heat scale
+//! pressure    (version 4+) width * height f32 air pressures over ambient
+//! oxygen      (version 7+) width * height f32 oxygen as a share of fresh air's
+//! wind        (version 5+) per 8x8 wind square, row-major: steady vx, vy then gust vx,
+//!             vy, all f32
+//! chunks      (version 2+) u32 count, then one byte per chunk: 1 if it is awake next tick
+//! ```
+//!
+//! Cell material ids are written as they are at save time and mapped back through the
+//! material table by name on load, so saves survive materials being added or reordered.
+//! Version 1 saves predate sleeping chunks and load with every chunk awake. Versions 1 and
+//! 2 predate real temperatures; their heat has no meaning now, so each cell starts at the
+//! temperature its material is painted at, ambient for most. Saves
+//! before version 4 have no air pressure and load at ambient, and saves before version 5
+//! load without wind. Saves before version 7 load with fresh air everywhere.
+//! Older velocities are scaled up to sub-cell steps.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::wind::WIND_CELL;
+use crate::{Cell, Material, SUBCELL, Simulation, props};
+use std::fmt;
+
+const MAGIC: &[u8; 4] = b"SSIM";
+const VERSION: u16 = 7;
+const CELL_BYTES: usize = 10;
+const OLD_CELL_BYTES: usize = 6;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    BadDimensions,
+    UnknownMaterial(String),
+    UnmappedMaterialId(u8),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a sand-sim snapshot"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {v}"),
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::BadDimensions => write!(f, "snapshot has invalid dimensions"),
+            SnapshotError::UnknownMaterial(name) => write!(f, "snapshot uses unknown material '{name}'"),
+            SnapshotError::UnmappedMaterialId(id) => write!(f, "snapshot cell uses material id {id} missing from its material table"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[cfg(feature = "wasm")]
+impl From<SnapshotError> for JsValue {
+    fn from(e: SnapshotError) -> Self {
+        JsValue::from_str(&e.to_string())
+    }
+}
+
+/// Forward-only reader over the snapshot bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+        let end = self.pos.checked_add(n).ok_or(SnapshotError::Truncated)?;
+        let s = self.bytes.get(self.pos..end).ok_or(SnapshotError::Truncated)?;
+        self.pos = end;
+        Ok(s)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32s(&mut self, n: usize) -> Result<Vec<f32>, SnapshotError> {
+        let bytes = self.take(n.checked_mul(4).ok_or(SnapshotError::BadDimensions)?)?;
+        Ok(bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Simulation {
+    /// Serialize the full simulation state
+    pub fn save(&self) -> Vec<u8> {
+        let len = self.cells.len();
+        let mut out = Vec::with_capacity(64 + len * (CELL_BYTES + 8));
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.push(self.generation);
+        out.extend_from_slice(&self.frame.to_le_bytes());
+        out.extend_from_slice(&self.rng.to_le_bytes());
+
+        out.push(Material::count() as u8);
+        for m in Material::all() {
+            let name = m.name();
+            out.push(m.id());
+            out.push(name.len() as u8);
+            out.extend_from_slice(name.as_bytes());
+        }
+
+        for c in &self.cells {
+            out.extend_from_slice(&[c.material.id(), c.ra, c.rb, c.clock]);
+            out.extend_from_slice(&c.vx.to_le_bytes());
+            out.extend_from_slice(&c.vy.to_le_bytes());
+            out.extend_from_slice(&[c.sx as u8, c.sy as u8]);
+        }
+        for t in &self.temp {
+            out.extend_from_slice(&t.to_le_bytes());
+        }
+        for p in &self.pressure {
+            out.extend_from_slice(&p.to_le_bytes());
+        }
+        for o in &self.oxygen {
+            out.extend_from_slice(&o.to_le_bytes());
+        }
+        for (steady, gust) in self.wind.steady.iter().zip(&self.wind.gusts) {
+            for v in steady.iter().chain(gust) {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+
+        let pending = self.chunks.pending();
+        out.extend_from_slice(&(pending.len() as u32).to_le_bytes());
+        out.extend(pending.iter().map(|&a| a as u8));
+        out
+    }
+
+    /// Rebuild a simulation from bytes produced by `save`
+    pub fn load(bytes: &[u8]) -> Result<Simulation, SnapshotError> {
+        let mut r = Reader { bytes, pos: 0 };
+        if r.take(4)? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = r.u16()?;
+        if version == 0 || version > VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let width = r.u32()?;
+        let height = r.u32()?;
+        let len = (width as usize).checked_mul(height as usize).ok_or(SnapshotError::BadDimensions)?;
+        if width == 0 || height == 0 || u32::try_from(len).is_err() {
+            return Err(SnapshotError::BadDimensions);
+        }
+        let generation = r.u8()?;
+        let frame = r.u32()?;
+        let rng = r.u64()?;
+
+        // saved id -> current material
+        let mut id_map: [Option<Material>; 256] = [None; 256];
+        for _ in 0..r.u8()? {
+            let id = r.u8()?;
+            let name_len = r.u8()? as usize;
+            let name = String::from_utf8_lossy(r.take(name_len)?);
+            id_map[id as usize] = Some(Material::from_name(&name).ok_or_else(|| SnapshotError::UnknownMaterial(name.into_owned()))?);
+        }
+
+        let cell_bytes = if version >= 6 { CELL_BYTES } else { OLD_CELL_BYTES };
+        let records = r.take(len.checked_mul(cell_bytes).ok_or(SnapshotError::BadDimensions)?)?;
+        let mut cells = Vec::with_capacity(len);
+        for rec in records.chunks_exact(cell_bytes) {
+            let material = id_map[rec[0] as usize].ok_or(SnapshotError::UnmappedMaterialId(rec[0]))?;
+            let (vx, vy, sx, sy) = if version >= 6 {
+                (i16::from_le_bytes([rec[4], rec[5]]), i16::from_le_bytes([rec[6], rec[7]]), rec[8] as i8, rec[9] as i8)
+            } else {
+                (rec[4] as i8 as i16 * SUBCELL, rec[5] as i8 as i16 * SUBCELL, 0, 0)
+            };
+            cells.push(Cell { material, ra: rec[1], rb: rec[2], clock: rec[3], vx, vy, sx, sy });
+        }
+        let temp = if version >= 3 {
+            Some(r.f32s(len)?)
+        } else {
+            r.take(len)?;
+            None
+        };
+        let pressure = if version >= 4 { Some(r.f32s(len)?) } else { None };
+        let oxygen = if version >= 7 { Some(r.f32s(len)?) } else { None };
+        let squares = (width.div_ceil(WIND_CELL) * height.div_ceil(WIND_CELL)) as usize;
+        let wind = if version >= 5 { Some(r.f32s(squares * 4)?) } else { None };
+        let pending = if version >= 2 {
+            let count = r.u32()? as usize;
+            Some(r.take(count)?.iter().map(|&b| b != 0).collect::<Vec<_>>())
+        } else {
+            None
+        };
+
+        let mut sim = Simulation::with_seed(width, height, 0);
+        sim.cells = cells;
+        if let Some(temp) = temp {
+            sim.temp = temp;
+        } else {
+            // start every cell where painting it would, so old lava is still molten
+            for (t, c) in sim.temp.iter_mut().zip(&sim.cells) {
+                let p = props(c.material);
+                if let Some(start) = p.source_temperature.or(p.initial_temperature) {
+                    *t = start;
+                }
+            }
+        }
+        if let Some(pressure) = pressure {
+            sim.pressure = pressure;
+        }
+        if let Some(oxygen) = oxygen {
+            sim.oxygen = oxygen;
+        }
+        if let Some(wind) = wind {
+            for (i, v) in wind.chunks_exact(4).enumerate() {
+                sim.wind.steady[i] = [v[0], v[1]];
+                sim.wind.gusts[i] = [v[2], v[3]];
+            }
+        }
+        sim.generation = generation;
+        sim.frame = frame;
+        sim.rng = rng;
+        sim.chunks.wake_all();
+        sim.write_pixels();
+        if let Some(pending) = pending {
+            if pending.len() != sim.chunks.pending().len() {
+                return Err(SnapshotError::BadDimensions);
+            }
+            sim.chunks.set_pending(&pending);
+        }
+        Ok(sim)
+    }
+}
@@ -1,10 +1,18 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::JsValue;
 
 /// Console logging helper
+#[cfg(feature = "wasm")]
 pub fn log(s: &str) {
     web_sys::console::log_1(&JsValue::from_str(s));
 }
 
+/// Native logging helper, writes to stderr
+#[cfg(not(feature = "wasm"))]
+pub fn log(s: &str) {
+    eprintln!("{s}");
+}
+
 /// println!-style macro that uses the log function
 #[macro_export]
 macro_rules! log {
@@ -0,0 +1,114 @@
+//! Wind: air velocity, in cells per tick, on a coarse grid of `WIND_CELL` squares.
+//!
+//! Each square has a steady wind, set for the whole grid or a region with `set_wind` and
+//! `set_wind_rect`, plus gusts fed in by whatever blows. Gusts spread into neighbouring
+//! squares and die down over a few dozen ticks. Materials with a `wind_response` pick up
+//! that share of the wind every tick, unless the cell upwind of them shelters them. Along
+//! the wind, the top layer of a pile is always exposed, so it blows off bit by bit.
+
+use crate::chunks::CHUNK_SIZE;
+use crate::{Group, Material, SUBCELL, SimAPI, Simulation, props};
+
+/// Side of a wind square, in cells
+pub(crate) const WIND_CELL: u32 = 8;
+
+// chunks updating at once reach into disjoint halves of the chunk between them, so squares
+// must not straddle those halves for gusts fed from both sides to land in different squares
+const _: () = assert!((CHUNK_SIZE / 2).is_multiple_of(WIND_CELL));
+
+/// Share of a gust kept per tick
+const GUST_DECAY: f32 = 0.95;
+
+/// Share of a gust passed to each neighbouring square per tick
+const GUST_SPREAD: f32 = 0.1;
+
+/// Gusts slower than this die out, so calm squares stop being visited
+const CALM: f32 = 0.01;
+
+pub(crate) struct Wind {
+    pub cols: u32,
+    pub rows: u32,
+    /// wind per square until changed
+    pub steady: Vec<[f32; 2]>,
+    /// transient wind per square on top of it
+    pub gusts: Vec<[f32; 2]>,
+    next: Vec<[f32; 2]>,
+}
+
+impl Wind {
+    pub fn new(width: u32, height: u32) -> Self {
+        let cols = width.div_ceil(WIND_CELL);
+        let rows = height.div_ceil(WIND_CELL);
+        let n = (cols * rows) as usize;
+        Wind { cols, rows, steady: vec![[0.0; 2]; n], gusts: vec![[0.0; 2]; n], next: vec![[0.0; 2]; n] }
+    }
+
+    /// Index of the square holding cell (x, y)
+    #[inline]
+    pub fn square(&self, x: i32, y: i32) -> usize {
+        ((y as u32 / WIND_CELL) * self.cols + x as u32 / WIND_CELL) as usize
+    }
+}
+
+/// Whether a cell of this material lets the wind through to what's behind it
+#[inline]
+fn open(api: &SimAPI, dx: i32, dy: i32) -> bool {
+    api.in_bounds(dx, dy) && matches!(props(api.get(dx, dy).material).group, Group::Empty | Group::Gas | Group::Fire)
+}
+
+/// Velocity the wind adds to this cell this tick, as (vx, vy)
+pub(crate) fn catch(api: &mut SimAPI, response: f32) -> (i16, i16) {
+    let [wx, wy] = api.wind_here();
+    let (sx, sy) = (wx.signum() as i32, wy.signum() as i32);
+    let mut push = (0, 0);
+    // only what the upwind side leaves exposed; along the ground, the top layer too
+    if wx != 0.0 && (open(api, -sx, 0) || open(api, 0, -1)) {
+        push.0 = (wx * response * SUBCELL as f32) as i16;
+        // resting grains only move now and then, don't let the chunk doze off between
+        if api.in_bounds(sx, 0) && api.get(sx, 0).material == Material::Empty {
+            api.keep_awake();
+        }
+    }
+    if wy != 0.0 && open(api, 0, -sy) {
+        push.1 = (wy * response * SUBCELL as f32) as i16;
+    }
+    push
+}
+
+impl Simulation {
+    /// Spread and calm the gusts, waking the chunks they still blow through
+    pub(crate) fn blow(&mut self) {
+        let wind = &mut self.wind;
+        if wind.gusts.iter().all(|g| *g == [0.0; 2]) {
+            return;
+        }
+
+        let (cols, rows) = (wind.cols as usize, wind.rows as usize);
+        for y in 0..rows {
+            for x in 0..cols {
+                let i = y * cols + x;
+                let g = wind.gusts[i];
+                let mut next = [g[0] * (1.0 - 4.0 * GUST_SPREAD), g[1] * (1.0 - 4.0 * GUST_SPREAD)];
+                // off the grid it blows away
+                for (j, inside) in [(i.wrapping_sub(cols), y > 0), (i + cols, y + 1 < rows), (i.wrapping_sub(1), x > 0), (i + 1, x + 1 < cols)] {
+                    if inside {
+                        next[0] += wind.gusts[j][0] * GUST_SPREAD;
+                        next[1] += wind.gusts[j][1] * GUST_SPREAD;
+                    }
+                }
+                for v in &mut next {
+                    *v *= GUST_DECAY;
+                    if v.abs() < CALM {
+                        *v = 0.0;
+                    }
+                }
+                wind.next[i] = next;
+                if next != [0.0; 2] {
+                    let (cx, cy) = ((x as u32 * WIND_CELL) as i32, (y as u32 * WIND_CELL) as i32);
+                    self.chunks.wake(cx + WIND_CELL as i32 / 2, cy + WIND_CELL as i32 / 2);
+                }
+            }
+        }
+        std::mem::swap(&mut wind.gusts, &mut wind.next);
+    }
+}
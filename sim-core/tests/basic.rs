@@ -1,27 +1,36 @@
 use sim_core::Simulation;
-use wasm_bindgen_test::*;
 
-#[wasm_bindgen_test]
+#[test]
 fn creates_simulation() {
     let sim = Simulation::new(10, 10);
     assert_eq!(sim.width(), 10);
     assert_eq!(sim.height(), 10);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn step_does_not_panic() {
     let mut sim = Simulation::new(10, 10);
     sim.step(1);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn paint_and_count() {
     let mut sim = Simulation::new(10, 10);
     sim.paint_circle(10, 10, 3, 2);
     assert!(sim.count_mat(2) > 0);
 }
 
-#[wasm_bindgen_test]
+#[test]
+fn paint_circle_takes_any_radius() {
+    let mut sim = Simulation::with_seed(10, 10, 1);
+    sim.paint_circle(5, 5, u32::MAX, 2);
+    assert_eq!(sim.count_mat(2), 100);
+    // centred far off to the right, its edge reaches in to x = 5, and to x = 4 on its centre row
+    sim.paint_circle(u32::MAX, 5, u32::MAX - 4, 3);
+    assert_eq!(sim.count_mat(3), 5 * 10 + 1);
+}
+
+#[test]
 fn props_array_covers_all_materials() {
     for id in 0..17u8 {
         let mat = sim_core::Material::from_id(id);
@@ -34,31 +43,31 @@ fn props_array_covers_all_materials() {
     }
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn color_of_matches_expected() {
     use sim_core::{Cell, Material, color_of};
 
-    let cell = Cell { material: Material::Sand, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0 };
+    let cell = Cell { material: Material::Sand, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 };
     let c = color_of(cell);
     assert_eq!(c, [210, 185, 110, 255]);
 
-    let cell = Cell { material: Material::Empty, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0 };
+    let cell = Cell { material: Material::Empty, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 };
     let c = color_of(cell);
     assert_eq!(c, [0, 0, 0, 255]);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn glow_of_matches_expected() {
     use sim_core::{Cell, Material, glow_of};
 
-    let cell = Cell { material: Material::Lava, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0 };
+    let cell = Cell { material: Material::Lava, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 };
     assert_eq!(glow_of(cell), [255, 60, 0, 160]);
 
-    let cell = Cell { material: Material::Sand, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0 };
+    let cell = Cell { material: Material::Sand, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 };
     assert_eq!(glow_of(cell), [0, 0, 0, 0]);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn group_check_covers_solids_and_powders() {
     use sim_core::{Group, Material, props};
 
@@ -73,7 +82,7 @@ fn group_check_covers_solids_and_powders() {
     }
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn from_id_round_trips() {
     use sim_core::Material;
 
@@ -83,57 +92,57 @@ fn from_id_round_trips() {
     assert_eq!(Material::from_id(255).id(), 0);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn cell_velocity_defaults_to_zero() {
     use sim_core::{Cell, Material};
 
-    let cell = Cell { material: Material::Sand, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0 };
+    let cell = Cell { material: Material::Sand, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 };
     assert_eq!(cell.vx, 0);
     assert_eq!(cell.vy, 0);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn cell_velocity_can_be_set() {
     use sim_core::{Cell, Material};
 
-    let cell = Cell { material: Material::Sand, ra: 0, rb: 0, clock: 0, vx: -3, vy: 5 };
+    let cell = Cell { material: Material::Sand, ra: 0, rb: 0, clock: 0, vx: -3, vy: 5, sx: 0, sy: 0 };
     assert_eq!(cell.vx, -3);
     assert_eq!(cell.vy, 5);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn cell_spread_preserves_velocity() {
     use sim_core::{Cell, Material};
 
-    let cell = Cell { material: Material::Sand, ra: 0, rb: 0, clock: 0, vx: 2, vy: -4 };
+    let cell = Cell { material: Material::Sand, ra: 0, rb: 0, clock: 0, vx: 2, vy: -4, sx: 0, sy: 0 };
     let updated = Cell { rb: 10, ..cell };
     assert_eq!(updated.vx, 2);
     assert_eq!(updated.vy, -4);
     assert_eq!(updated.rb, 10);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn velocity_props_populated() {
     use sim_core::{Material, props};
 
     let sand = props(Material::Sand);
-    assert_eq!(sand.gravity, 1);
+    assert_eq!(sand.gravity, 1.0);
     assert_eq!(sand.terminal_velocity, 10);
-    assert_eq!(sand.slide_acceleration, 1);
+    assert_eq!(sand.slide_acceleration, 1.0);
 
     let smoke = props(Material::Smoke);
-    assert_eq!(smoke.gravity, -1);
+    assert_eq!(smoke.gravity, -1.0);
     assert_eq!(smoke.terminal_velocity, 1);
 
     let stone = props(Material::Stone);
-    assert_eq!(stone.gravity, 0);
+    assert_eq!(stone.gravity, 0.0);
     assert_eq!(stone.terminal_velocity, 0);
 
     let ice = props(Material::Ice);
     assert_eq!(ice.surface_slipperiness, 0.98);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn gravity_direction_correct() {
     use sim_core::{Material, props};
 
@@ -142,17 +151,17 @@ fn gravity_direction_correct() {
     let stationary = [Material::Empty, Material::Wall, Material::Stone, Material::Wood, Material::Obsidian, Material::Ice, Material::Fire];
 
     for mat in falling {
-        assert_eq!(props(mat).gravity, 1, "{:?} should fall", mat);
+        assert_eq!(props(mat).gravity, 1.0, "{:?} should fall", mat);
     }
     for mat in rising {
-        assert_eq!(props(mat).gravity, -1, "{:?} should rise", mat);
+        assert_eq!(props(mat).gravity, -1.0, "{:?} should rise", mat);
     }
     for mat in stationary {
-        assert_eq!(props(mat).gravity, 0, "{:?} should be stationary", mat);
+        assert_eq!(props(mat).gravity, 0.0, "{:?} should be stationary", mat);
     }
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn sand_preserved_after_falling() {
     let mut sim = Simulation::new(20, 40);
     sim.paint_circle(10, 5, 2, 2);
@@ -162,7 +171,7 @@ fn sand_preserved_after_falling() {
     assert_eq!(sim.count_mat(2), initial);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn water_preserved_after_falling() {
     let mut sim = Simulation::new(20, 40);
     sim.paint_circle(10, 5, 2, 3);
@@ -172,7 +181,7 @@ fn water_preserved_after_falling() {
     assert_eq!(sim.count_mat(3), initial);
 }
 
-#[wasm_bindgen_test]
+#[test]
 fn smoke_fades_over_time() {
     let mut sim = Simulation::new(20, 20);
     sim.paint_circle(10, 10, 3, 7);
@@ -181,3 +190,948 @@ fn smoke_fades_over_time() {
     sim.step(200);
     assert_eq!(sim.count_mat(7), 0);
 }
+
+#[test]
+fn same_seed_steps_identically() {
+    let run = || {
+        let mut sim = Simulation::with_seed(40, 40, 42);
+        sim.paint_circle(10, 8, 4, 2);
+        sim.paint_circle(28, 8, 4, 3);
+        sim.paint_circle(20, 30, 3, 9);
+        sim.paint_circle(20, 20, 2, 6);
+        sim.step(60);
+        sim
+    };
+    let a = run();
+    let b = run();
+    assert_eq!(a.cells(), b.cells());
+    assert_eq!(a.temperatures(), b.temperatures());
+    assert_eq!(a.pixels(), b.pixels());
+    assert_eq!(a.glow_pixels(), b.glow_pixels());
+}
+
+#[test]
+fn different_seeds_diverge() {
+    let a = Simulation::with_seed(20, 20, 1);
+    let b = Simulation::with_seed(20, 20, 2);
+    assert_ne!(a.cells(), b.cells());
+}
+
+#[test]
+fn native_new_uses_default_seed() {
+    let a = Simulation::new(16, 16);
+    let b = Simulation::with_seed(16, 16, sim_core::DEFAULT_SEED);
+    assert_eq!(a.cells(), b.cells());
+}
+
+#[test]
+fn snapshot_round_trips() {
+    let mut sim = Simulation::with_seed(30, 30, 7);
+    sim.paint_circle(10, 5, 3, 2);
+    sim.paint_circle(20, 20, 3, 9);
+    sim.set_wind_rect(0, 0, 12, 12, 0.5, -0.25);
+    sim.step(20);
+
+    let bytes = sim.save();
+    let mut loaded = Simulation::load(&bytes).unwrap();
+    assert_eq!(loaded.width(), 30);
+    assert_eq!(loaded.height(), 30);
+    assert_eq!(loaded.cells(), sim.cells());
+    assert_eq!(loaded.temperatures(), sim.temperatures());
+    assert_eq!(loaded.pressures(), sim.pressures());
+    assert_eq!(loaded.oxygen(), sim.oxygen());
+    assert_eq!(loaded.wind_at(3, 3), [0.5, -0.25]);
+    assert_eq!(loaded.pixels(), sim.pixels());
+    assert_eq!(loaded.save(), bytes);
+
+    // rng, generation and frame come along, so both copies keep stepping in lockstep
+    sim.step(20);
+    loaded.step(20);
+    assert_eq!(loaded.cells(), sim.cells());
+    assert_eq!(loaded.save(), sim.save());
+}
+
+/// A version 1 save, as written before chunks could sleep or cells held a temperature: the
+/// header, a material table of (saved id, name), then a six byte record per cell of the
+/// saved material id and ra, and a byte of the old heat scale per cell
+fn version_1_save(width: u32, height: u32, table: &[(u8, &str)], cells: &[u8]) -> Vec<u8> {
+    let mut out = b"SSIM".to_vec();
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.push(0);
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&1u64.to_le_bytes());
+    out.push(table.len() as u8);
+    for &(id, name) in table {
+        out.extend_from_slice(&[id, name.len() as u8]);
+        out.extend_from_slice(name.as_bytes());
+    }
+    for &id in cells {
+        out.extend_from_slice(&[id, 100, 0, 0, 0, 0]);
+    }
+    out.extend(cells.iter().map(|_| 0));
+    out
+}
+
+#[test]
+fn snapshot_maps_material_ids_by_name() {
+    use sim_core::Material;
+
+    // a save from a build where the enum had sand and water the other way round
+    let (sand, water) = (Material::Water.id(), Material::Sand.id());
+    let mut cells = [Material::Empty.id(); 64];
+    cells[3 * 8 + 3] = sand;
+    let bytes = version_1_save(8, 8, &[(Material::Empty.id(), "empty"), (sand, "sand"), (water, "water")], &cells);
+
+    let loaded = Simulation::load(&bytes).unwrap();
+    assert_eq!(loaded.cells()[3 * 8 + 3].material, Material::Sand);
+    assert_eq!(loaded.count_mat(Material::Water.id()), 0);
+}
+
+#[test]
+fn legacy_snapshots_start_cells_at_their_own_temperature() {
+    use sim_core::{AMBIENT_TEMPERATURE, Material};
+
+    let mut cells = [Material::Empty; 64];
+    (cells[27], cells[28]) = (Material::Lava, Material::Ice);
+    let table = [(Material::Empty.id(), "empty"), (Material::Lava.id(), "lava"), (Material::Ice.id(), "ice")];
+    let bytes = version_1_save(8, 8, &table, &cells.map(Material::id));
+
+    let mut old = Simulation::load(&bytes).unwrap();
+    let temps = old.temperatures();
+    assert_eq!((temps[27], temps[28], temps[0]), (1200.0, -10.0, AMBIENT_TEMPERATURE));
+    old.step(5);
+    assert_eq!(old.count_mat(Material::Stone.id()), 0, "the lava is still molten");
+}
+
+#[test]
+fn snapshot_rejects_bad_input() {
+    use sim_core::SnapshotError;
+
+    let bytes = Simulation::with_seed(8, 8, 1).save();
+    assert_eq!(Simulation::load(b"nope").err(), Some(SnapshotError::BadMagic));
+    assert_eq!(Simulation::load(&bytes[..bytes.len() - 1]).err(), Some(SnapshotError::Truncated));
+
+    let mut future = bytes.clone();
+    future[4] = 0xFF;
+    assert!(matches!(Simulation::load(&future), Err(SnapshotError::UnsupportedVersion(_))));
+}
+
+#[test]
+fn scene_builds_simulation() {
+    use sim_core::Material;
+
+    let src = "
+        # floor with a pile above it
+        size 40 30
+        seed 9
+        rect 0 28 39 29 stone
+        circle 20 10 3 sand
+        cell 1 1 3   # ids work too
+    ";
+    let sim = Simulation::from_scene(src, None).unwrap();
+    assert_eq!((sim.width(), sim.height()), (40, 30));
+    assert_eq!(sim.count_mat(Material::Stone.id()), 80);
+    assert!(sim.count_mat(Material::Sand.id()) > 0);
+    assert_eq!(sim.cells()[40 + 1].material, Material::Water);
+
+    // the seed argument wins over the scene's seed line
+    let same = Simulation::from_scene(src, None).unwrap();
+    let other = Simulation::from_scene(src, Some(10)).unwrap();
+    assert_eq!(same.cells(), sim.cells());
+    assert_ne!(other.cells(), sim.cells());
+}
+
+#[test]
+fn scene_reports_bad_lines() {
+    let err = Simulation::from_scene("size 10 10\ncircle 5 5 2 plutonium\n", None).err().unwrap();
+    assert_eq!(err.line, 2);
+    assert!(Simulation::from_scene("cell 1 1 sand\nsize 10 10\n", None).is_err());
+    assert!(Simulation::from_scene("explode 1 1\n", None).is_err());
+    assert!(Simulation::from_scene("size 10 10\nwind 1 0 2\n", None).is_err());
+    let err = Simulation::from_scene("size 70000 70000\n", None).err().unwrap();
+    assert_eq!(err.to_string(), "line 1: size 70000x70000 is too large");
+}
+
+#[test]
+fn settled_world_goes_to_sleep() {
+    use sim_core::Material;
+
+    let mut sim = Simulation::with_seed(96, 96, 4);
+    sim.paint_rect(0, 90, 95, 95, Material::Stone.id());
+    sim.paint_circle(20, 70, 8, Material::Sand.id());
+    sim.paint_circle(70, 60, 6, Material::Ash.id());
+    let sand = sim.count_mat(Material::Sand.id());
+
+    sim.step(400);
+    assert_eq!(sim.awake_chunks(), 0);
+    assert_eq!(sim.count_mat(Material::Sand.id()), sand);
+
+    // asleep means nothing changes, not just that nothing is visited
+    let settled = sim.cells().to_vec();
+    sim.step(10);
+    assert_eq!(sim.cells(), &settled[..]);
+
+    // painting wakes the chunk it lands in
+    sim.set_cell(70, 10, Material::Sand.id());
+    assert!(sim.awake_chunks() > 0);
+    sim.step(150);
+    assert_eq!(sim.count_mat(Material::Sand.id()), sand + 1);
+}
+
+#[test]
+fn snapshot_keeps_sleeping_chunks() {
+    use sim_core::Material;
+
+    let mut sim = Simulation::with_seed(96, 64, 5);
+    sim.paint_rect(0, 60, 95, 63, Material::Stone.id());
+    sim.paint_circle(20, 40, 6, Material::Sand.id());
+    sim.step(300);
+    sim.paint_circle(80, 10, 3, Material::Water.id());
+    sim.step(5);
+
+    let mut loaded = Simulation::load(&sim.save()).unwrap();
+    assert_eq!(loaded.awake_chunks(), sim.awake_chunks());
+    sim.step(50);
+    loaded.step(50);
+    assert_eq!(loaded.cells(), sim.cells());
+
+    // version 1 saves have no chunk section and load fully awake
+    let mut v1 = Simulation::with_seed(40, 40, 5).save();
+    v1[4] = 1;
+    v1.truncate(v1.len() - 4 - 4);
+    let old = Simulation::load(&v1).unwrap();
+    assert_eq!(old.awake_chunks(), 4);
+}
+
+/// Cells of `material` left and right of column `cx`, the column itself excluded
+fn split_counts(sim: &Simulation, material: sim_core::Material, cx: u32) -> (usize, usize) {
+    let w = sim.width();
+    let mut left = 0;
+    let mut right = 0;
+    for (i, c) in sim.cells().iter().enumerate() {
+        let x = i as u32 % w;
+        if c.material == material && x < cx {
+            left += 1;
+        } else if c.material == material && x > cx {
+            right += 1;
+        }
+    }
+    (left, right)
+}
+
+#[test]
+fn sand_pile_stays_symmetric() {
+    use sim_core::Material;
+
+    let (mut total_left, mut total_right) = (0, 0);
+    for seed in 0..4 {
+        let mut sim = Simulation::with_seed(128, 96, seed);
+        sim.paint_rect(0, 92, 127, 95, Material::Stone.id());
+        // pour from a single column so any sideways bias shows up in the pile
+        for _ in 0..400 {
+            sim.set_cell(64, 2, Material::Sand.id());
+            sim.step(1);
+        }
+        sim.step(200);
+        let (left, right) = split_counts(&sim, Material::Sand, 64);
+        assert!(left.abs_diff(right) * 5 < left + right, "seed {seed}: {left} left vs {right} right");
+        total_left += left;
+        total_right += right;
+    }
+    assert!(total_left.abs_diff(total_right) * 12 < total_left + total_right, "{total_left} left vs {total_right} right");
+}
+
+#[test]
+fn water_spreads_symmetrically() {
+    use sim_core::Material;
+
+    let (mut total_left, mut total_right) = (0, 0);
+    for seed in 0..4 {
+        let mut sim = Simulation::with_seed(160, 64, seed);
+        sim.paint_rect(0, 60, 159, 63, Material::Stone.id());
+        sim.paint_rect(75, 20, 85, 40, Material::Water.id());
+        sim.step(40);
+        let (left, right) = split_counts(&sim, Material::Water, 80);
+        assert!(left.abs_diff(right) * 4 < left + right, "seed {seed}: {left} left vs {right} right");
+        total_left += left;
+        total_right += right;
+    }
+    assert!(total_left.abs_diff(total_right) * 10 < total_left + total_right, "{total_left} left vs {total_right} right");
+}
+
+#[test]
+fn resizing_keeps_the_cells_that_fit() {
+    use sim_core::Material;
+
+    // a sand pile on the floor and lava in the top corner
+    let mut sim = Simulation::with_seed(20, 10, 1);
+    sim.paint_rect(0, 8, 19, 9, Material::Sand.id());
+    sim.set_cell(19, 0, Material::Lava.id());
+
+    // growing keeps the floor at the bottom, with new air above and beside it
+    let big = sim.resized(30, 15);
+    assert_eq!((big.width(), big.height()), (30, 15));
+    assert_eq!(big.count_mat(Material::Sand.id()), 40);
+    assert_eq!(big.cells()[13 * 30].material, Material::Sand);
+    assert_eq!(big.cells()[5 * 30 + 19].material, Material::Lava);
+    assert_eq!(big.temperatures()[5 * 30 + 19], sim.temperatures()[19]);
+    assert_eq!(big.frame(), sim.frame());
+
+    // shrinking drops the cells off the top and right
+    let small = sim.resized(10, 5);
+    assert_eq!(small.count_mat(Material::Sand.id()), 20);
+    assert_eq!(small.count_mat(Material::Lava.id()), 0);
+}
+
+#[test]
+fn empty_grids_step() {
+    for threads in [1, 4] {
+        let mut sim = Simulation::with_seed(0, 0, 1);
+        sim.set_threads(threads);
+        sim.step(2);
+        assert_eq!(sim.frame(), 1);
+        assert_eq!(sim.wind_at(0, 0), [0.0; 2]);
+    }
+    Simulation::new(0, 0).step(1);
+}
+
+#[test]
+fn thread_count_does_not_change_results() {
+    let run = |threads: usize| {
+        // several chunks wide, so every checkerboard colour has work to share
+        let mut sim = Simulation::with_seed(200, 150, 9);
+        sim.set_threads(threads);
+        sim.paint_rect(10, 10, 190, 40, 2);
+        sim.paint_rect(20, 60, 180, 90, 3);
+        sim.paint_circle(60, 120, 10, 9);
+        sim.paint_circle(140, 120, 8, 6);
+        sim.paint_rect(0, 140, 199, 149, 5);
+        sim.step(80);
+        sim
+    };
+    let a = run(1);
+    for threads in [2, 4] {
+        let b = run(threads);
+        assert_eq!(a.cells(), b.cells(), "{threads} threads");
+        assert_eq!(a.temperatures(), b.temperatures(), "{threads} threads");
+        assert_eq!(a.pressures(), b.pressures(), "{threads} threads");
+    }
+}
+
+#[test]
+fn material_table_adds_materials() {
+    use sim_core::{Behaviour, Group, MaterialTable};
+
+    let builtin = MaterialTable::builtin();
+    assert_eq!(builtin.len(), 19);
+
+    let src = format!(
+        "{}\n[[material]]\nid = 30\nname = \"slime\"\ngroup = \"liquid\"\ncolour = [90, 200, 60, 255]\ncolour_variation = [8, 8, 8]\ngravity = 1\ndispersion = 2\nviscosity = 4\n",
+        include_str!("../materials.toml")
+    );
+    let table = MaterialTable::from_toml(&src).unwrap();
+    assert_eq!(table.len(), 20);
+    let slime = table.find("slime").unwrap();
+    assert_eq!(slime.id(), 30);
+    let p = table.props(slime);
+    assert_eq!(p.group, Group::Liquid);
+    // behaviour follows the group unless named
+    assert_eq!(p.behaviour, Behaviour::Water);
+    assert_eq!((p.dispersion, p.viscosity, p.air_drag), (2, 4, 1.0));
+    assert_eq!(table.info(slime).label, "slime");
+    // the rules oil and acid once had are the shared liquid flow now
+    let src = src.replace("id = 30\nname = \"slime\"\ngroup = \"liquid\"\n", "id = 30\nname = \"slime\"\ngroup = \"liquid\"\nbehaviour = \"oil\"\n");
+    let table = MaterialTable::from_toml(&src).unwrap();
+    assert_eq!(table.props(table.find("slime").unwrap()).behaviour, Behaviour::Water);
+}
+
+#[test]
+fn material_table_rejects_bad_documents() {
+    use sim_core::{MaterialTable, MaterialsError};
+
+    let builtin = include_str!("../materials.toml");
+    let with = |extra: &str| MaterialTable::from_toml(&format!("{builtin}\n[[material]]\n{extra}\n")).err().unwrap();
+    let base = "group = \"powder\"\ncolour = [1, 2, 3, 255]\ncolour_variation = [4, 4, 4]";
+
+    assert!(matches!(MaterialTable::from_toml("[[material]]\nid = \"x\""), Err(MaterialsError::Parse(_))));
+    assert_eq!(with(&format!("id = 3\nname = \"mud\"\n{base}")).to_string(), "material 'mud': id 3 is already used by 'water'");
+    assert_eq!(with(&format!("id = 40\nname = \"sand\"\n{base}")).to_string(), "material 'sand': defined twice");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\ngravty = 1")).to_string(), "material 'mud': unknown key 'gravty'");
+    assert_eq!(
+        with(&format!("id = 40\nname = \"mud\"\n{base}\nheat_capacity = 0.5")).to_string(),
+        "material 'mud': heat_capacity must be at least 1 and thermal_conductivity 0 to 1"
+    );
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nstrength = -1.0")).to_string(), "material 'mud': strength can't be negative");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nrubble = \"gravel\"")).to_string(), "material 'mud': rubble is unknown material 'gravel'");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nwind_response = 1.5")).to_string(), "material 'mud': wind_response must be 0 to 1");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\ndensity = 0.0")).to_string(), "material 'mud': density must be positive");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nterminal_velocity = -3")).to_string(), "material 'mud': terminal_velocity must be 0 to 16");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nterminal_velocity = 60")).to_string(), "material 'mud': terminal_velocity must be 0 to 16");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\ndispersion = 17")).to_string(), "material 'mud': dispersion can be at most 16");
+    assert_eq!(
+        with(&format!("id = 40\nname = \"mud\"\n{base}\nterminal_velocity = 10\ndispersion = 10")).to_string(),
+        "material 'mud': terminal_velocity and dispersion together reach 20 cells, at most 16"
+    );
+    assert_eq!(
+        with(&format!("id = 40\nname = \"mud\"\n{base}\nterminal_velocity = 16")).to_string(),
+        "material 'mud': terminal_velocity and dispersion together reach 17 cells, at most 16"
+    );
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nviscosity = 0")).to_string(), "material 'mud': viscosity must be at least 1");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nrestitution = 2.0")).to_string(), "material 'mud': restitution must be 0 to 1");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nbuoyant = true")).to_string(), "material 'mud': only solids can be buoyant");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nflammability = 2.0")).to_string(), "material 'mud': flammability must be 0 to 1");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nburn_duration = 200")).to_string(), "material 'mud': burn_duration can be at most 127");
+    assert_eq!(with(&format!("id = 40\nname = \"mud\"\n{base}\nfreezes_into = \"sand\"")).to_string(), "material 'mud': freezes_into material 'sand' has no melt_point");
+    assert_eq!(
+        with(&format!("id = 40\nname = \"mud\"\n{base}\ncombustion_products = [\"ash\", \"tar\"]")).to_string(),
+        "material 'mud': combustion product is unknown material 'tar'"
+    );
+    assert_eq!(
+        with(&format!("id = 40\nname = \"Mud\"\n{base}")),
+        MaterialsError::Invalid("material 'Mud': names must be lowercase, without spaces and at most 255 bytes".into())
+    );
+
+    let without_water = builtin.replacen("name = \"water\"", "name = \"wet\"", 1);
+    assert_eq!(MaterialTable::from_toml(&without_water).err().unwrap().to_string(), "built-in material 'water' must be defined with id 3");
+}
+
+#[test]
+fn reactions_run_from_the_table() {
+    use sim_core::Material;
+
+    // lava poured onto water turns to obsidian and gives off steam
+    let mut sim = Simulation::with_seed(16, 16, 5);
+    sim.paint_rect(0, 12, 15, 15, Material::Water.id());
+    sim.set_cell(8, 11, Material::Lava.id());
+    sim.step(1);
+    assert_eq!(sim.count_mat(Material::Lava.id()), 0);
+    assert_eq!(sim.count_mat(Material::Obsidian.id()), 1);
+    assert!(sim.count_mat(Material::Steam.id()) > 1);
+
+    // fire next to water goes out straight away
+    let mut sim = Simulation::with_seed(16, 16, 5);
+    sim.paint_rect(0, 15, 15, 15, Material::Wall.id());
+    sim.set_cell(8, 14, Material::Water.id());
+    sim.set_cell(9, 14, Material::Fire.id());
+    sim.step(1);
+    assert_eq!(sim.count_mat(Material::Fire.id()), 0);
+
+    // acid eats through sand, one 1-in-5 chance per tick, without going to sleep on it
+    let mut sim = Simulation::with_seed(16, 16, 5);
+    sim.paint_rect(0, 8, 15, 15, Material::Sand.id());
+    sim.set_cell(8, 7, Material::Acid.id());
+    let sand = sim.count_mat(Material::Sand.id());
+    sim.step(60);
+    assert!(sim.count_mat(Material::Sand.id()) < sand);
+}
+
+#[test]
+fn material_table_checks_reactions() {
+    use sim_core::{MaterialTable, MaterialsError};
+
+    let builtin = include_str!("../materials.toml");
+    let n = MaterialTable::builtin().reaction_count();
+    assert!(n > 0);
+
+    let with = |extra: &str| MaterialTable::from_toml(&format!("{builtin}\n[[reaction]]\n{extra}\n"));
+    assert_eq!(with("a = \"sand\"\nb = \"water\"\nb_becomes = \"stone\"\nrate = 3").unwrap().reaction_count(), n + 1);
+    assert_eq!(with("a = \"sand\"\nb = \"mud\"").err().unwrap().to_string(), format!("reaction {} (sand + mud): unknown material 'mud'", n + 1));
+    assert_eq!(
+        with("a = \"sand\"\nb = \"water\"\nmin_temp = 9\nmax_temp = 3").err().unwrap().to_string(),
+        format!("reaction {} (sand + water): min_temp is above max_temp", n + 1)
+    );
+    assert!(matches!(with("a = \"sand\"\nb = \"water\"\nrat = 3"), Err(MaterialsError::Parse(_))));
+    assert!(matches!(with("a = \"sand\"\nb = \"water\"\nat = [\"left\"]"), Err(MaterialsError::Parse(_))));
+}
+
+#[test]
+fn heat_leaves_enclosed_spaces() {
+    use sim_core::{AMBIENT_TEMPERATURE, Material};
+
+    let mut sim = Simulation::with_seed(20, 20, 2);
+    sim.paint_rect(0, 0, 19, 19, Material::Wall.id());
+    sim.paint_rect(1, 1, 18, 18, Material::Empty.id());
+    sim.set_cell(10, 18, Material::Fire.id());
+    sim.step(20);
+    assert!(sim.temperatures().iter().any(|&t| t > 100.0));
+
+    // the fire burns out and the sealed box cools back down
+    sim.step(2500);
+    assert_eq!(sim.count_mat(Material::Fire.id()), 0);
+    assert!(sim.temperatures().iter().all(|&t| t == AMBIENT_TEMPERATURE));
+}
+
+#[test]
+fn clear_resets_temperature() {
+    use sim_core::{AMBIENT_TEMPERATURE, Material};
+
+    let mut sim = Simulation::with_seed(20, 20, 2);
+    sim.paint_rect(0, 15, 19, 19, Material::Lava.id());
+    sim.step(20);
+    assert!(sim.temperatures().iter().any(|&t| t > 100.0));
+
+    // nothing left to hold the heat, so the empty grid is back at room temperature
+    sim.clear();
+    sim.step(1);
+    assert!(sim.temperatures().iter().all(|&t| t == AMBIENT_TEMPERATURE));
+}
+
+#[test]
+fn conductors_pass_heat_faster() {
+    use sim_core::Material;
+
+    // lava under a stone column and a wall column, both with air around them
+    let mut sim = Simulation::with_seed(40, 24, 2);
+    sim.paint_rect(0, 20, 39, 23, Material::Lava.id());
+    sim.paint_rect(0, 19, 39, 19, Material::Obsidian.id());
+    sim.paint_rect(8, 10, 10, 18, Material::Stone.id());
+    sim.paint_rect(28, 10, 30, 18, Material::Wall.id());
+    sim.step(150);
+    let t = sim.temperatures();
+    assert!(t[14 * 40 + 9] > t[14 * 40 + 29] + 10.0, "stone {} wall {}", t[14 * 40 + 9], t[14 * 40 + 29]);
+}
+
+#[test]
+fn radiant_heat_crosses_gaps_but_not_walls() {
+    use sim_core::Material;
+
+    // wood hanging two cells above a lava basin, with and without a wall over the lava
+    let wood_temp = |shield: bool| {
+        let mut sim = Simulation::with_seed(40, 40, 3);
+        sim.paint_rect(0, 36, 39, 39, Material::Wall.id());
+        sim.paint_rect(0, 28, 4, 35, Material::Wall.id());
+        sim.paint_rect(35, 28, 39, 35, Material::Wall.id());
+        sim.paint_rect(5, 30, 34, 35, Material::Lava.id());
+        if shield {
+            sim.paint_rect(5, 29, 34, 29, Material::Wall.id());
+        }
+        sim.paint_rect(15, 26, 24, 27, Material::Wood.id());
+        sim.step(60);
+        sim.temperatures()[27 * 40 + 20]
+    };
+    let (open, shielded) = (wood_temp(false), wood_temp(true));
+    assert!(open > 150.0 && open > shielded + 100.0, "open {open} shielded {shielded}");
+}
+
+#[test]
+fn sealed_steam_bursts_weak_walls() {
+    use sim_core::Material;
+
+    // a box of water on a lava hotplate, walled in by `side`
+    let boil = |side: Material| {
+        let mut sim = Simulation::with_seed(40, 40, 4);
+        sim.paint_rect(5, 10, 34, 30, side.id());
+        sim.paint_rect(6, 11, 33, 29, Material::Empty.id());
+        sim.paint_rect(6, 24, 33, 29, Material::Water.id());
+        sim.paint_rect(5, 30, 34, 30, Material::Wall.id());
+        sim.paint_rect(0, 31, 39, 39, Material::Wall.id());
+        sim.paint_rect(6, 31, 33, 35, Material::Lava.id());
+        // the hotplate crusts over into stone as it cools, so only count the box
+        let box_walls = |sim: &Simulation| sim.cells()[..30 * 40].iter().filter(|c| c.material == side).count();
+        let walls = box_walls(&sim);
+        let mut peak = 0.0f32;
+        for _ in 0..40 {
+            sim.step(10);
+            peak = peak.max(sim.pressures()[15 * 40 + 20]);
+            if box_walls(&sim) < walls {
+                return (true, peak);
+            }
+        }
+        (false, peak)
+    };
+
+    let (burst, peak) = boil(Material::Wall);
+    assert!(!burst && peak > 10.0, "wall box holds, at {peak}");
+    let (burst, _) = boil(Material::Stone);
+    assert!(burst, "stone box bursts");
+}
+
+#[test]
+fn steam_condensing_on_a_cold_roof_takes_its_pressure_back() {
+    use sim_core::Material;
+
+    // a sealed box of water on a hotplate, with and without ice on its roof
+    let boil = |roof: Material| {
+        let mut sim = Simulation::with_seed(40, 40, 4);
+        sim.paint_rect(5, 8, 34, 9, roof.id());
+        sim.paint_rect(5, 10, 34, 30, Material::Wall.id());
+        sim.paint_rect(6, 11, 33, 29, Material::Empty.id());
+        sim.paint_rect(6, 24, 33, 29, Material::Water.id());
+        sim.paint_rect(0, 31, 39, 39, Material::Wall.id());
+        sim.paint_rect(6, 31, 33, 35, Material::Lava.id());
+        sim.step(600);
+        assert_eq!(sim.count_mat(Material::Water.id()) + sim.count_mat(Material::Steam.id()), 168);
+        (sim.pressures()[15 * 40 + 20], sim.count_mat(Material::Steam.id()))
+    };
+
+    // the steam that rains back off the cold roof no longer presses on the walls
+    let (warm, warm_steam) = boil(Material::Empty);
+    let (cold, cold_steam) = boil(Material::Ice);
+    assert!(cold_steam < warm_steam && cold < warm * 0.6, "pressure {cold} under ice, {warm} without");
+}
+
+#[test]
+fn pressure_vents_out_of_open_air() {
+    use sim_core::Material;
+
+    let mut open = Simulation::with_seed(40, 40, 4);
+    open.set_pressure_rect(15, 15, 24, 24, 50.0);
+    open.step(30);
+    assert!(open.pressures().iter().all(|&p| p == 0.0));
+
+    let mut sealed = Simulation::with_seed(40, 40, 4);
+    sealed.paint_rect(10, 10, 29, 29, Material::Wall.id());
+    sealed.paint_rect(11, 11, 28, 28, Material::Empty.id());
+    sealed.set_pressure_rect(15, 15, 24, 24, 50.0);
+    sealed.step(30);
+    let p = sealed.pressures();
+    assert!(p[11 * 40 + 11] > 10.0 && (p[20 * 40 + 20] - p[11 * 40 + 11]).abs() < 1.0, "evened out inside");
+    assert_eq!(p[5 * 40 + 5], 0.0);
+}
+
+#[test]
+fn pressure_pushes_what_moves() {
+    use sim_core::Material;
+
+    // sand just right of a high pressure pocket is blown further right as it falls
+    let mut sim = Simulation::with_seed(40, 40, 4);
+    sim.set_cell(20, 10, Material::Sand.id());
+    sim.set_pressure_rect(12, 6, 19, 14, 40.0);
+    sim.step(1);
+    let x = sim.cells().iter().position(|c| c.material == Material::Sand).unwrap() % 40;
+    assert!(x > 20, "sand at x {x}");
+}
+
+/// Mean x of the cells of a material
+fn mean_x(sim: &Simulation, material: sim_core::Material) -> f32 {
+    let w = sim.width() as usize;
+    let xs: Vec<usize> = sim.cells().iter().enumerate().filter(|(_, c)| c.material == material).map(|(i, _)| i % w).collect();
+    xs.iter().sum::<usize>() as f32 / xs.len() as f32
+}
+
+#[test]
+fn wind_bends_smoke() {
+    use sim_core::Material;
+
+    let plume = |wind: f32| {
+        let mut sim = Simulation::from_scene("size 120 80\nrect 0 75 119 79 wall\nrect 40 60 60 74 wood\nrect 40 59 60 59 fire\n", Some(1)).unwrap();
+        sim.set_wind(wind, 0.0);
+        sim.step(100);
+        mean_x(&sim, Material::Smoke)
+    };
+    let (calm, windy) = (plume(0.0), plume(1.0));
+    assert!(windy > calm + 20.0, "calm {calm} windy {windy}");
+}
+
+#[test]
+fn wind_strips_ash_but_not_sand() {
+    use sim_core::Material;
+
+    let shift = |m: Material| {
+        let mut sim = Simulation::from_scene(&format!("size 120 80\nrect 0 75 119 79 wall\ncircle 40 60 10 {}\nstep 200\n", m.name()), Some(1)).unwrap();
+        let before = mean_x(&sim, m);
+        // only over the left half, the right stays calm
+        sim.set_wind_rect(0, 0, 63, 79, 1.0, 0.0);
+        assert_eq!((sim.wind_at(63, 0), sim.wind_at(64, 0)), ([1.0, 0.0], [0.0, 0.0]));
+        sim.step(300);
+        mean_x(&sim, m) - before
+    };
+    let (ash, sand) = (shift(Material::Ash), shift(Material::Sand));
+    assert!(ash > 5.0, "ash moved {ash}");
+    assert_eq!(sand, 0.0);
+}
+
+#[test]
+fn explosions_fling_what_walls_do_not_shelter() {
+    use sim_core::Material;
+
+    // a charge left of a sand heap, with or without a wall between them
+    let sand_x = |shield: bool| {
+        let mut sim = Simulation::with_seed(100, 60, 3);
+        sim.paint_rect(0, 55, 99, 59, Material::Wall.id());
+        sim.paint_rect(57, 50, 60, 54, Material::Sand.id());
+        if shield {
+            sim.paint_rect(55, 40, 55, 54, Material::Wall.id());
+        }
+        sim.paint_circle(50, 53, 3, Material::Gunpowder.id());
+        sim.step(30);
+        sim.set_cell(50, 49, Material::Fire.id());
+        sim.step(60);
+        mean_x(&sim, Material::Sand)
+    };
+    let (open, shielded) = (sand_x(false), sand_x(true));
+    // unmoved, the heap slumps to a mean of about 59
+    assert!(open > shielded + 4.0 && shielded < 60.0, "open {open} shielded {shielded}");
+}
+
+#[test]
+fn bigger_charges_break_more() {
+    use sim_core::Material;
+
+    let stone_left = |r: u32| {
+        let mut sim = Simulation::with_seed(60, 60, 3);
+        sim.paint_rect(20, 20, 40, 40, Material::Stone.id());
+        sim.paint_rect(22, 22, 38, 38, Material::Empty.id());
+        sim.paint_rect(0, 41, 59, 59, Material::Wall.id());
+        sim.paint_circle(30, 38 - r, r, Material::Gunpowder.id());
+        sim.set_cell(30, 37 - 2 * r, Material::Fire.id());
+        sim.step(60);
+        sim.count_mat(Material::Stone.id())
+    };
+    let (small, big) = (stone_left(1), stone_left(3));
+    assert!(small < 149 && big + 20 < small, "small {small} big {big}");
+}
+
+#[test]
+fn slow_velocities_build_up_across_ticks() {
+    use sim_core::{Material, SUBCELL};
+
+    // stone hangs where it is put, so give it a quarter cell per tick of drift
+    let mut sim = Simulation::with_seed(40, 10, 1);
+    sim.set_cell(5, 5, Material::Stone.id());
+    sim.set_velocity(5, 5, SUBCELL / 4, 0);
+
+    sim.step(40);
+    let x = sim.cells().iter().position(|c| c.material == Material::Stone).unwrap() % 40;
+    assert_eq!(x, 15);
+}
+
+#[test]
+fn falling_grains_bounce_off_the_floor() {
+    use sim_core::Material;
+
+    let mut sim = Simulation::with_seed(20, 80, 1);
+    sim.paint_rect(0, 75, 19, 79, Material::Wall.id());
+    sim.set_cell(10, 5, Material::Sand.id());
+    let mut bounced = false;
+    for _ in 0..40 {
+        sim.step(1);
+        let sand = sim.cells().iter().find(|c| c.material == Material::Sand).unwrap();
+        bounced |= sand.vy < 0;
+    }
+    assert!(bounced);
+    // and it comes to rest on the floor
+    assert_eq!(sim.cells().iter().position(|c| c.material == Material::Sand), Some(74 * 20 + 10));
+}
+
+#[test]
+fn water_falling_into_a_pool_splashes() {
+    use sim_core::Material;
+
+    let mut sim = Simulation::with_seed(100, 80, 2);
+    sim.paint_rect(0, 75, 99, 79, Material::Wall.id());
+    sim.paint_rect(0, 65, 99, 74, Material::Water.id());
+    sim.paint_rect(48, 5, 51, 12, Material::Water.id());
+    let mut splashed = false;
+    for _ in 0..60 {
+        sim.step(1);
+        splashed |= sim.cells()[..65 * 100].iter().any(|c| c.material == Material::Water && c.vy < 0);
+    }
+    assert!(splashed);
+}
+
+#[test]
+fn fluids_and_powders_layer_by_density() {
+    use sim_core::Material;
+
+    let mean_y = |sim: &Simulation, material: Material| {
+        let ys: Vec<usize> = sim.cells().iter().enumerate().filter(|(_, c)| c.material == material).map(|(i, _)| i / 40).collect();
+        ys.iter().sum::<usize>() as f32 / ys.len() as f32
+    };
+    let tank = "size 40 60\nrect 0 50 39 59 wall\nrect 0 0 9 49 wall\nrect 30 0 39 49 wall\n";
+
+    // stacked the wrong way up, lightest at the bottom
+    let mut sim = Simulation::from_scene(&format!("{tank}rect 10 41 29 49 oil\nrect 10 35 29 40 water\nrect 10 30 29 34 sand\n"), Some(1)).unwrap();
+    sim.step(200);
+    let (sand, water, oil) = (mean_y(&sim, Material::Sand), mean_y(&sim, Material::Water), mean_y(&sim, Material::Oil));
+    assert!(sand > water && water > oil, "sand {sand}, water {water}, oil {oil}");
+
+    // and ash floats up out of oil
+    let mut sim = Simulation::from_scene(&format!("{tank}rect 10 47 29 49 ash\nrect 10 41 29 46 oil\n"), Some(1)).unwrap();
+    sim.step(200);
+    assert!(mean_y(&sim, Material::Ash) < mean_y(&sim, Material::Oil));
+}
+
+#[test]
+fn connected_basins_level_out() {
+    use sim_core::Material;
+
+    // two basins split by a wall that stops short of the floor, only the left one filled
+    let mut sim = Simulation::from_scene("size 60 60\nrect 0 50 59 59 wall\nrect 0 0 4 49 wall\nrect 55 0 59 49 wall\nrect 29 10 30 44 wall\nrect 5 20 28 49 water\n", Some(1)).unwrap();
+    let surface = |sim: &Simulation, x0: usize, x1: usize| (0..60).find(|&y| (x0..=x1).any(|x| sim.cells()[y * 60 + x].material == Material::Water)).unwrap();
+    sim.step(400);
+    let (left, right) = (surface(&sim, 5, 28), surface(&sim, 31, 54));
+    assert!(left.abs_diff(right) <= 1, "left at {left}, right at {right}");
+    assert!(right < 44, "right basin filled above the gap, to {right}");
+}
+
+#[test]
+fn buoyant_solids_float_on_denser_liquids_and_sink_in_lighter() {
+    use sim_core::Material;
+
+    let tank = |liquid: Material, solid: Material, y0: u32| {
+        let scene = format!("size 60 60\nrect 0 55 59 59 wall\nrect 0 0 4 54 wall\nrect 55 0 59 54 wall\nrect 5 20 54 54 {}\nrect 25 {y0} 34 {} {}\n", liquid.name(), y0 + 9, solid.name());
+        let mut sim = Simulation::from_scene(&scene, Some(1)).unwrap();
+        sim.step(200);
+        let ys: Vec<usize> = sim.cells().iter().enumerate().filter(|(_, c)| c.material == solid).map(|(i, _)| i / 60).collect();
+        let surface = (0..60).find(|&y| sim.cells()[y * 60 + 8].material == liquid).unwrap();
+        (*ys.iter().min().unwrap(), *ys.iter().max().unwrap(), surface)
+    };
+
+    // wood let go at the bottom of a pool comes up and rides with part of it showing
+    let (top, bottom, surface) = tank(Material::Water, Material::Wood, 44);
+    assert!(top < surface && bottom > surface, "wood {top} to {bottom}, water at {surface}");
+    // ice is denser than oil, so it goes down instead
+    let (top, _, _) = tank(Material::Oil, Material::Ice, 22);
+    assert!(top > 25, "ice top at {top}");
+}
+
+#[test]
+fn fuel_burns_for_as_long_as_it_lasts() {
+    use sim_core::Material;
+
+    // a pool of oil in a trough, lit at one end
+    let mut sim = Simulation::with_seed(40, 20, 3);
+    sim.paint_rect(0, 15, 39, 19, Material::Wall.id());
+    sim.paint_rect(0, 10, 9, 14, Material::Wall.id());
+    sim.paint_rect(30, 10, 39, 14, Material::Wall.id());
+    sim.paint_rect(10, 13, 29, 14, Material::Oil.id());
+    sim.set_cell(10, 12, Material::Fire.id());
+
+    // long after a lone flame would have died the pool is still alight
+    sim.step(150);
+    let oil = sim.count_mat(Material::Oil.id());
+    assert!(oil > 20 && oil < 40, "{oil} oil left");
+    assert!(sim.count_mat(Material::Fire.id()) > 0);
+
+    // and once it's used up its flames go out with it
+    sim.step(450);
+    assert_eq!(sim.count_mat(Material::Oil.id()), 0);
+    assert_eq!(sim.count_mat(Material::Fire.id()), 0);
+}
+
+#[test]
+fn burning_wood_chars_and_falls() {
+    use sim_core::{Cell, Material, color_of};
+
+    // a beam sticking out of a wall, its far end over a basin of lava
+    let scene = "size 60 60\nrect 0 55 59 59 wall\nrect 0 20 9 54 wall\nrect 10 25 40 26 wood\nrect 28 29 46 34 wall\nrect 30 29 44 32 lava\n";
+    let mut sim = Simulation::from_scene(scene, Some(1)).unwrap();
+    sim.step(400);
+    // the lava's heat lights it, the weakened end breaks off and burnt-out wood leaves charcoal
+    let fallen = sim.cells().iter().enumerate().filter(|&(i, c)| c.material == Material::Wood && i / 60 > 26).count();
+    assert!(fallen > 0);
+    assert!(sim.count_mat(Material::from_name("charcoal").unwrap().id()) > 0);
+
+    // charring shows
+    let fresh = Cell { material: Material::Wood, ra: 128, rb: 0, clock: 0, vx: 0, vy: 0, sx: 0, sy: 0 };
+    let charred = Cell { rb: 100, ..fresh };
+    assert!(color_of(charred)[0] < color_of(fresh)[0] / 2);
+}
+
+#[test]
+fn fires_go_out_without_air() {
+    use sim_core::{AMBIENT_OXYGEN, Material};
+
+    // a pool of oil lit along its surface, in a stone box that is open at the top or sealed
+    let burn = |sealed: bool| {
+        let lid = if sealed { "" } else { "rect 7 5 32 6 empty\n" };
+        let scene = format!("size 40 30\nrect 5 5 34 24 stone\nrect 7 7 32 22 empty\n{lid}rect 7 19 32 22 oil\nrect 7 18 32 18 fire\n");
+        let mut sim = Simulation::from_scene(&scene, Some(1)).unwrap();
+        sim.step(600);
+        sim
+    };
+
+    let open = burn(false);
+    assert_eq!(open.count_mat(Material::Oil.id()), 0);
+
+    // the sealed box runs out of air and leaves most of its oil unburnt
+    let sealed = burn(true);
+    assert!(sealed.count_mat(Material::Oil.id()) > 50);
+    assert_eq!(sealed.count_mat(Material::Fire.id()), 0);
+    let inside = sealed.oxygen()[12 * 40 + 20];
+    assert!(inside < AMBIENT_OXYGEN / 2.0, "oxygen {inside}");
+}
+
+#[test]
+fn sealed_rooms_share_their_air_across_chunks() {
+    use sim_core::AMBIENT_OXYGEN;
+
+    // a sealed room several chunks wide, burning at one end while the far end settles
+    let scene = "size 160 40\nrect 5 5 154 24 stone\nrect 7 7 152 22 empty\nrect 7 19 30 22 oil\nrect 7 18 30 18 fire\n";
+    let mut sim = Simulation::from_scene(scene, Some(1)).unwrap();
+    sim.step(200);
+
+    // the whole room is one body of air, so the far end gives up its oxygen too
+    let far = sim.oxygen()[12 * 160 + 140];
+    assert!(far < AMBIENT_OXYGEN, "oxygen {far}");
+
+    // and a reload, which wakes every chunk, carries on the same
+    let mut loaded = Simulation::load(&sim.save()).unwrap();
+    sim.step(300);
+    loaded.step(300);
+    assert_eq!(loaded.cells(), sim.cells());
+    assert_eq!(loaded.oxygen(), sim.oxygen());
+}
+
+#[test]
+fn lava_cools_and_sets_into_stone() {
+    use sim_core::Material;
+
+    // a block of lava dropped on a floor wide enough for it to run to the edges
+    let scene = "size 60 40\nrect 0 35 59 39 wall\nrect 20 20 39 34 lava\n";
+    let mut sim = Simulation::from_scene(scene, Some(1)).unwrap();
+    sim.step(400);
+    // it spreads while hot, stops as it cools and sets into as much stone as there was lava
+    assert_eq!(sim.count_mat(Material::Lava.id()), 0);
+    assert_eq!(sim.count_mat(Material::Stone.id()), 300);
+    let xs: Vec<usize> = sim.cells().iter().enumerate().filter(|(_, c)| c.material == Material::Stone).map(|(i, _)| i % 60).collect();
+    let (left, right) = (*xs.iter().min().unwrap(), *xs.iter().max().unwrap());
+    assert!(left < 20 && right > 39 && left > 0 && right < 59, "spread from {left} to {right}");
+}
+
+#[test]
+fn water_cycles_in_a_closed_terrarium() {
+    use sim_core::Material;
+
+    // a sealed box of water with lava under its floor and ice on its roof
+    let scene = "size 40 44\nrect 0 0 39 1 ice\nrect 0 2 39 43 wall\nrect 2 3 37 35 empty\nrect 2 32 37 35 water\nrect 2 37 37 42 lava\n";
+    let mut sim = Simulation::from_scene(scene, Some(1)).unwrap();
+    let vapour = |sim: &Simulation| sim.count_mat(Material::Steam.id());
+    let water = |sim: &Simulation| sim.count_mat(Material::Water.id());
+
+    // the hot floor boils it off and it rains back down from the cold roof
+    sim.step(300);
+    let drops = sim.cells()[..25 * 40].iter().filter(|c| c.material == Material::Water).count();
+    assert!(vapour(&sim) > 30 && drops > 0, "steam {} drops {drops}", vapour(&sim));
+    assert_eq!(water(&sim) + vapour(&sim), 144);
+
+    // once the floor has cooled the pool still evaporates at room temperature, more slowly,
+    // and keeps raining back off the roof, none of it lost
+    sim.step(1200);
+    for _ in 0..3 {
+        let (mut evaporated, mut condensed) = (0, 0);
+        for _ in 0..1000 {
+            let before = vapour(&sim);
+            sim.step(1);
+            evaporated += vapour(&sim).saturating_sub(before);
+            condensed += before.saturating_sub(vapour(&sim));
+        }
+        assert!(evaporated > 0 && condensed > 0, "evaporated {evaporated} condensed {condensed}");
+        assert_eq!(water(&sim) + vapour(&sim), 144);
+    }
+}
+
+#[test]
+fn still_water_evaporates_at_room_temperature() {
+    use sim_core::Material;
+
+    // a shallow pool with nothing to warm it slowly turns to steam, which hangs in the air
+    let mut sim = Simulation::from_scene("size 60 60\nrect 10 50 40 51 water\n", Some(1)).unwrap();
+    sim.step(6000);
+    let (water, steam) = (sim.count_mat(Material::Water.id()), sim.count_mat(Material::Steam.id()));
+    assert!(water < 62 && steam > 0, "water {water} steam {steam}");
+    assert_eq!(water + steam, 62);
+}
@@ -0,0 +1,102 @@
+use sim_core::Simulation;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sand-sim-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn sand_sim(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_sand-sim")).args(args).output().unwrap()
+}
+
+#[test]
+fn runs_scene_and_writes_outputs() {
+    let dir = temp_dir("scene");
+    let scene = dir.join("scene.txt");
+    std::fs::write(&scene, "size 32 24\nrect 0 22 31 23 stone\ncircle 16 6 3 sand\n").unwrap();
+    let snap = dir.join("out.ssim");
+    let png = dir.join("out.png");
+    let frames = dir.join("frames");
+
+    let out = sand_sim(&[
+        "-i",
+        scene.to_str().unwrap(),
+        "-n",
+        "20",
+        "-s",
+        "5",
+        "-o",
+        snap.to_str().unwrap(),
+        "-p",
+        png.to_str().unwrap(),
+        "-f",
+        frames.to_str().unwrap(),
+        "-e",
+        "10",
+        "--glow",
+        "--heat",
+        "-q",
+    ]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+
+    // the binary's result matches stepping the same scene in-process
+    let mut expected = Simulation::from_scene(&std::fs::read_to_string(&scene).unwrap(), Some(5)).unwrap();
+    expected.step(10);
+    expected.step(10);
+    let saved = Simulation::load(&std::fs::read(&snap).unwrap()).unwrap();
+    assert_eq!(saved.cells(), expected.cells());
+
+    assert!(std::fs::read(&png).unwrap().starts_with(b"\x89PNG"));
+    for name in ["frame_000000.png", "frame_000010.png", "frame_000020.png"] {
+        assert!(frames.join(name).exists(), "missing {name}");
+    }
+}
+
+#[test]
+fn continues_from_snapshot() {
+    let dir = temp_dir("snapshot");
+    let mut sim = Simulation::with_seed(24, 24, 3);
+    sim.paint_circle(12, 6, 3, 3);
+    sim.step(5);
+    let input = dir.join("in.ssim");
+    let output = dir.join("out.ssim");
+    std::fs::write(&input, sim.save()).unwrap();
+
+    let out = sand_sim(&["-i", input.to_str().unwrap(), "-n", "15", "-t", "3", "-o", output.to_str().unwrap(), "-q"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+
+    sim.step(15);
+    assert_eq!(std::fs::read(&output).unwrap(), sim.save());
+}
+
+#[test]
+fn rejects_unknown_arguments() {
+    let out = sand_sim(&["--bogus"]);
+    assert!(!out.status.success());
+}
+
+#[test]
+fn uses_custom_materials() {
+    let dir = temp_dir("materials");
+    let materials = dir.join("materials.toml");
+    let extra = "\n[[material]]\nid = 40\nname = \"redsand\"\ngroup = \"powder\"\ncolour = [200, 60, 40, 255]\ncolour_variation = [6, 6, 6]\ngravity = 1\nterminal_velocity = 6\n";
+    std::fs::write(&materials, format!("{}{extra}", include_str!("../materials.toml"))).unwrap();
+    let scene = dir.join("scene.txt");
+    std::fs::write(&scene, "size 32 24\nrect 10 2 20 6 redsand\n").unwrap();
+    let snap = dir.join("out.ssim");
+
+    let out = sand_sim(&["-m", materials.to_str().unwrap(), "-i", scene.to_str().unwrap(), "-n", "30", "-o", snap.to_str().unwrap(), "-q"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    // the snapshot's material table names the new material
+    let bytes = std::fs::read(&snap).unwrap();
+    assert!(bytes.windows(7).any(|w| w == b"redsand"));
+
+    // without the table the scene doesn't know it
+    let out = sand_sim(&["-i", scene.to_str().unwrap(), "-q"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown material 'redsand'"));
+}
@@ -1,28 +1,16 @@
 import { useRef, useState } from "react";
-import { useSimulation, COUNT_IDS } from "./hooks/useSimulation";
+import { useSimulation } from "./hooks/useSimulation";
 import { usePainting } from "./hooks/usePainting";
 import "./App.css";
 
-type Material = { id: number; label: string; color: string; key: string; group: string | null; desc: string };
-
-const MATERIALS: Material[] = [
-  { id: 0,  label: "Erase",     color: "#111111", key: "", group: null,      desc: "Remove material" },
-  { id: 2,  label: "Sand",      color: "#d2b96e", key: "", group: "Powders", desc: "Falls and piles; sinks through water" },
-  { id: 16, label: "Gunpowder", color: "#3c3732", key: "", group: "Powders", desc: "Falls like sand; explodes on contact with fire, lava, or ember; chain-detonates adjacent gunpowder" },
-  { id: 3,  label: "Water",     color: "#286ed2", key: "", group: "Liquids", desc: "Flows and spreads; boils to steam near heat; extinguishes fire on contact" },
-  { id: 9,  label: "Lava",      color: "#cf460a", key: "", group: "Liquids", desc: "Viscous and extremely hot; ignites wood; solidifies to obsidian when it contacts water" },
-  { id: 12, label: "Acid",      color: "#03a02d", key: "", group: "Liquids", desc: "Dissolves most materials over time; even obsidian and walls erode slowly" },
-  { id: 14, label: "Oil",       color: "#141210", key: "", group: "Liquids", desc: "Floats on water; slowly catches fire from adjacent flames; spreads across the surface as it burns" },
-  { id: 4,  label: "Stone",     color: "#6e6e73", key: "", group: "Solids",  desc: "Stable solid; extremely rarely melts to lava when near a heat source as intense as lava" },
-  { id: 1,  label: "Wall",      color: "#64605a", key: "", group: "Solids",  desc: "Nearly indestructible; immune to fire and lava; dissolves very slowly in acid" },
-  { id: 5,  label: "Wood",      color: "#784b1e", key: "", group: "Solids",  desc: "Burns when touched by fire, lava, or ember; produces smoke and ash" },
-  { id: 11, label: "Obsidian",  color: "#19102a", key: "", group: "Solids",  desc: "Hard solid formed when lava contacts water; immune to fire and lava; dissolves slowly in acid" },
-  { id: 15, label: "Ice",       color: "#a0d8f0", key: "", group: "Solids",  desc: "Melts to water near heat; slowly spreads to freeze adjacent water" },
-  { id: 6,  label: "Fire",      color: "#dc3c0a", key: "", group: "Fire",    desc: "Spreads directly to wood; ignites nearby oil; extinguished by water; drips slowly downward" },
-  { id: 13, label: "Ember",     color: "#ffa014", key: "", group: "Fire",    desc: "Rises through smoke and steam; short-lived; small chance to reignite as fire" },
-];
-
-const GROUPS = ["Powders", "Liquids", "Solids", "Fire"] as const;
+// Palette sections, keyed by the material table's group; empty (Erase) sits above them
+const GROUPS = [
+  { group: "powder", label: "Powders" },
+  { group: "liquid", label: "Liquids" },
+  { group: "solid", label: "Solids" },
+  { group: "gas", label: "Gases" },
+  { group: "fire", label: "Fire" },
+] as const;
 
 // const KEY_MAP: Record<string, number> = {
 //   "1": 0,
@@ -46,9 +34,12 @@ export default function App() {
   const [paused, setPaused] = useState(false);
   const [ticksPerStep, setTicksPerStep] = useState(4);
   const [showHeat, setShowHeat] = useState(false);
+  const [wind, setWind] = useState(0);
 
   const canvasRef = useRef<HTMLCanvasElement>(null);
-  const sim = useSimulation(canvasRef, W, H, paused, ticksPerStep, showHeat);
+  const sim = useSimulation(canvasRef, W, H, paused, ticksPerStep, showHeat, wind);
+
+  const palette = sim.materials.filter((m) => m.palette);
 
   const { onPointerDown, onPointerMove, onPointerUp } = usePainting(canvasRef, W, H, (x, y) => sim.paint(x, y, currentMaterial, brushRadius));
 
@@ -72,25 +63,29 @@ export default function App() {
           SandSim <span className="fps">{sim.fps} fps</span>
         </h1>
         <div className="mat-grid">
-          {MATERIALS.filter((m) => m.group === null).map((m) => (
-            <button key={m.id} className={"mat-btn" + (currentMaterial === m.id ? " active" : "")} onClick={() => setCurrentMaterial(m.id)} title={m.desc}>
-              <span className="mat-swatch" style={{ background: m.color }} />
-              <span>{m.label}</span>
-              {m.key && <kbd>{m.key}</kbd>}
-            </button>
-          ))}
-          {GROUPS.map((group) => (
-            <div key={group}>
-              <div className="mat-group-label">{group}</div>
-              {MATERIALS.filter((m) => m.group === group).map((m) => (
-                <button key={m.id} className={"mat-btn" + (currentMaterial === m.id ? " active" : "")} onClick={() => setCurrentMaterial(m.id)} title={m.desc}>
-                  <span className="mat-swatch" style={{ background: m.color }} />
-                  <span>{m.label}</span>
-                  {m.key && <kbd>{m.key}</kbd>}
-                </button>
-              ))}
-            </div>
-          ))}
+          {palette
+            .filter((m) => m.group === "empty")
+            .map((m) => (
+              <button key={m.id} className={"mat-btn" + (currentMaterial === m.id ? " active" : "")} onClick={() => setCurrentMaterial(m.id)} title={m.description}>
+                <span className="mat-swatch" style={{ background: m.swatch }} />
+                <span>{m.label}</span>
+              </button>
+            ))}
+          {GROUPS.map(({ group, label }) => {
+            const mats = palette.filter((m) => m.group === group);
+            if (mats.length === 0) return null;
+            return (
+              <div key={group}>
+                <div className="mat-group-label">{label}</div>
+                {mats.map((m) => (
+                  <button key={m.id} className={"mat-btn" + (currentMaterial === m.id ? " active" : "")} onClick={() => setCurrentMaterial(m.id)} title={m.description}>
+                    <span className="mat-swatch" style={{ background: m.swatch }} />
+                    <span>{m.label}</span>
+                  </button>
+                ))}
+              </div>
+            );
+          })}
         </div>
       </aside>
 
@@ -109,6 +104,10 @@ export default function App() {
             <label>Speed — {ticksPerStep}x</label>
             <input type="range" min={1} max={16} value={ticksPerStep} onChange={(e) => setTicksPerStep(+e.target.value)} />
           </div>
+          <div className="ctrl-group">
+            <label>Wind — {wind > 0 ? "→" : wind < 0 ? "←" : ""} {Math.abs(wind)}</label>
+            <input type="range" min={-2} max={2} step={0.25} value={wind} onChange={(e) => setWind(+e.target.value)} />
+          </div>
           <div className="ctrl-group">
             <label>Width — {W}px</label>
             <input type="range" min={80} max={640} step={40} value={W} onChange={(e) => setW(+e.target.value)} />
@@ -141,13 +140,12 @@ export default function App() {
           <div className="ctrl-group counts">
             <label>Counts</label>
             <div className="count-grid">
-              {COUNT_IDS.map((id) => {
-                const mat = MATERIALS.find((m) => m.id === id);
-                const count = sim.counts[id] ?? 0;
-                if (!mat || count === 0) return null;
+              {sim.materials.map((mat) => {
+                const count = sim.counts[mat.id] ?? 0;
+                if (count === 0) return null;
                 return (
-                  <div key={id} className="count-row">
-                    <span className="count-swatch" style={{ background: mat.color }} />
+                  <div key={mat.id} className="count-row">
+                    <span className="count-swatch" style={{ background: mat.swatch }} />
                     <span>{mat.label}</span>
                     <span className="count-val">{count.toLocaleString()}</span>
                   </div>
@@ -1,12 +1,52 @@
 import { useCallback, useEffect, useRef, useState, type RefObject } from "react";
-import init, { Simulation } from "../wasm/sim_core.js";
+import init, { Simulation, materials as loadMaterialTable } from "../wasm/sim_core.js";
 
 const TPS = 60;
 const dt = 1000 / TPS; // ms per simulation tick
 
-export const COUNT_IDS = [2, 3, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+// One entry of the sim's material table (sim-core/materials.toml)
+export type Material = { id: number; name: string; label: string; description: string; group: string; swatch: string; palette: boolean };
 
-export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W: number, H: number, paused: boolean, ticksPerStep: number, showHeat: boolean) {
+// Copy the table out of wasm into plain objects, freeing the wasm-side copies
+function readMaterials(): Material[] {
+  return loadMaterialTable().map((m) => {
+    const { id, name, label, description, group, swatch, palette } = m;
+    m.free();
+    return { id, name, label, description, group, swatch, palette };
+  });
+}
+
+const SNAPSHOT_KEY = "sand-sim:snapshot";
+
+// localStorage only holds strings, so snapshots are stored base64 encoded
+function saveSnapshot(sim: Simulation) {
+  const bytes = sim.save();
+  let bin = "";
+  for (let i = 0; i < bytes.length; i += 0x8000) bin += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+  try {
+    localStorage.setItem(SNAPSHOT_KEY, btoa(bin));
+  } catch {
+    // quota exceeded or storage disabled, nothing to restore next time
+  }
+}
+
+// Restore the last autosave, copied into the current size if it was taken at another
+function loadSnapshot(W: number, H: number): Simulation | null {
+  const stored = localStorage.getItem(SNAPSHOT_KEY);
+  if (!stored) return null;
+  try {
+    const sim = Simulation.load(Uint8Array.from(atob(stored), (c) => c.charCodeAt(0)));
+    if (sim.width() === W && sim.height() === H) return sim;
+    const resized = sim.resized(W, H);
+    sim.free();
+    return resized;
+  } catch {
+    localStorage.removeItem(SNAPSHOT_KEY);
+  }
+  return null;
+}
+
+export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W: number, H: number, paused: boolean, ticksPerStep: number, showHeat: boolean, wind: number) {
   // Mutable values read/written inside the requestAnimationFrame loop — never trigger re-renders
   const simRef = useRef<Simulation | null>(null);
   const bufferRef = useRef<ArrayBuffer | null>(null); // WASM linear memory buffer
@@ -18,11 +58,14 @@ export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W:
   const pausedRef = useRef(paused);
   const ticksRef = useRef(ticksPerStep);
   const showHeatRef = useRef(showHeat);
+  const windRef = useRef(wind);
   pausedRef.current = paused;
   ticksRef.current = ticksPerStep;
   showHeatRef.current = showHeat;
+  windRef.current = wind;
 
   const [ready, setReady] = useState(false);
+  const [materials, setMaterials] = useState<Material[]>([]);
   const [counts, setCounts] = useState<Record<number, number>>({});
   const [fps, setFps] = useState(0);
 
@@ -37,8 +80,13 @@ export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W:
       if (cancelled) return;
 
       bufferRef.current = exports.memory.buffer;
+      const table = readMaterials();
+      setMaterials(table);
+      // everything but empty gets a row in the counts panel
+      const countIds = table.filter((m) => m.id !== 0).map((m) => m.id);
 
-      const sim = new Simulation(W, H);
+      const sim = loadSnapshot(W, H) ?? new Simulation(W, H);
+      sim.set_wind(windRef.current, 0);
       simRef.current = sim;
 
       // Off-screen targets at sim resolution; scaled up to canvas size via drawImage
@@ -50,7 +98,7 @@ export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W:
       const heatCtx = heatCanvas.getContext("2d")!;
       const heatImageData = heatCtx.createImageData(W, H);
 
-      // Precompute RGBA colour for every possible heat value (0–255) once at startup.
+      // Precompute RGBA colour for every heat level (0–255) once at startup.
       // Ramp: dark blue -> cyan -> yellow -> red.
       const heatLut = new Uint8Array(256 * 4); // 256 entries × 4 bytes (RGBA)
       for (let i = 0; i < 256; i++) {
@@ -126,10 +174,11 @@ export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W:
 
         // Heat overlay: full thermal view, dark blue (cold) -> cyan -> yellow -> red (hot)
         if (showHeatRef.current) {
-          const heatBuf = new Uint8Array(buf, sim.heat_ptr(), sim.heat_len());
+          const temps = new Float32Array(buf, sim.temperature_ptr(), sim.temperature_len());
           const px = heatImageData.data;
-          for (let i = 0; i < heatBuf.length; i++) {
-            const lut = heatBuf[i] * 4;
+          for (let i = 0; i < temps.length; i++) {
+            // same scale as heat_level in sim-core: 5 degrees a step from -20
+            const lut = Math.min(255, Math.max(0, Math.floor((temps[i] + 20) / 5))) * 4;
             const p = i * 4;
             px[p] = heatLut[lut];
             px[p + 1] = heatLut[lut + 1];
@@ -143,7 +192,7 @@ export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W:
         // Update counts and FPS every 30 frames
         if (++frame % 30 === 0) {
           const c: Record<number, number> = {};
-          for (const id of COUNT_IDS) c[id] = sim.count_mat(id);
+          for (const id of countIds) c[id] = sim.count_mat(id);
           setCounts(c);
           if (fpsTs) setFps(Math.round(30000 / (now - fpsTs)));
           fpsTs = now;
@@ -155,13 +204,30 @@ export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W:
       animRef.current = requestAnimationFrame(loop);
     }
 
+    // Autosave when the page goes away and when a resize replaces the simulation
+    const onPageHide = () => {
+      if (simRef.current) saveSnapshot(simRef.current);
+    };
+    window.addEventListener("pagehide", onPageHide);
+
     setup();
     return () => {
       cancelled = true;
       cancelAnimationFrame(animRef.current);
+      window.removeEventListener("pagehide", onPageHide);
+      if (simRef.current) {
+        saveSnapshot(simRef.current);
+        simRef.current.free();
+        simRef.current = null;
+      }
     };
   }, [W, H]);
 
+  // Steady wind across the whole grid; a fresh simulation picks it up in setup
+  useEffect(() => {
+    simRef.current?.set_wind(wind, 0);
+  }, [wind]);
+
   const paint = useCallback((x: number, y: number, material: number, brush: number) => {
     simRef.current?.paint_circle(x, y, brush, material);
   }, []);
@@ -169,5 +235,5 @@ export function useSimulation(canvasRef: RefObject<HTMLCanvasElement | null>, W:
   const step = useCallback(() => simRef.current?.step(ticksRef.current), []);
   const clear = useCallback(() => simRef.current?.clear(), []);
 
-  return { ready, counts, fps, paint, step, clear };
+  return { ready, materials, counts, fps, paint, step, clear };
 }